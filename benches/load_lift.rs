@@ -6,7 +6,11 @@ use marduk::{uaf, Config};
 
 fn load_lift_bin(b: &mut Bencher, bin: &&str) {
     let bin_path = format!("samples/artificial/{}", bin);
-    b.iter(|| uaf(&[bin_path.clone()], Config::LOAD_ONLY).run_rules());
+    b.iter(|| {
+        uaf(&[bin_path.clone()], Config::LOAD_ONLY)
+            .unwrap()
+            .run_rules()
+    });
 }
 
 fn load_lift_benches(c: &mut Criterion) {
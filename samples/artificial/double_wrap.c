@@ -0,0 +1,17 @@
+#include <stdlib.h>
+void release(char* p) {
+  free(p);
+}
+void drop(char* p) {
+  release(p);
+}
+int main () {
+  char* a = malloc(1);
+  char* b = malloc(1);
+  drop(a);
+  // good, b is only freed below. With one return site of context, both calls to release come
+  // from the same site in drop, so the free of a is also taken to free b.
+  *b = 'b';
+  drop(b);
+  return 0;
+}
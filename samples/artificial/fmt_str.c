@@ -0,0 +1,14 @@
+#include <stdlib.h>
+#include <stdio.h>
+
+int main (int argc, char** argv) {
+  char* out = malloc(8);
+  out[0] = 'a';
+  out[1] = 0;
+  printf("%s\n", out); // good
+  free(out);
+  printf("%d %lx %p\n", argc, (long)out, NULL); // good, the pointer is only printed as a number
+  printf("%d %d %d %d %d %.*f %s\n", 1, 2, 3, 4, 5, 2, 1.0, out); // bad, read from the stack
+  printf("%n\n", (int*)out); // bad, written through
+  return 0;
+}
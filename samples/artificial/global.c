@@ -0,0 +1,18 @@
+#include <stdlib.h>
+char* saved;
+
+void release() {
+  free(saved);
+}
+
+void touch() {
+  *saved = 'b';
+}
+
+int main () {
+  saved = malloc(1);
+  *saved = 'a'; // good
+  release();
+  touch(); // bad
+  return 0;
+}
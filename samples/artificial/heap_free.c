@@ -0,0 +1,11 @@
+#include <windows.h>
+
+int main () {
+  HANDLE heap = GetProcessHeap();
+  char* out = HeapAlloc(heap, 0, 1);
+  *out = 'a'; // good
+  // The object is the third argument, passed in R8
+  HeapFree(heap, 0, out);
+  *out = 'b'; // bad
+  return 0;
+}
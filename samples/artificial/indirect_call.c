@@ -0,0 +1,47 @@
+#include <stdlib.h>
+
+typedef void (*handler)(char*);
+
+static void keep(char* p) {
+  (void)p;
+}
+
+static void release(char* p) {
+  free(p);
+}
+
+// Read-only table of function pointers, laid out like a vtable
+struct ops {
+  handler keep;
+  handler release;
+};
+static const struct ops default_ops = {keep, release};
+
+struct object {
+  const struct ops* ops;
+};
+
+// Hide where the function pointers come from, so calls through them stay indirect
+__attribute__((noipa)) static handler pick(void) {
+  return release;
+}
+
+__attribute__((noipa)) static void init(struct object* obj) {
+  obj->ops = &default_ops;
+}
+
+int main (int argc, char** argv) {
+  handler cb = pick();
+  char* a = malloc(1);
+  *a = 'a'; // good
+  cb(a);
+  *a = 'b'; // bad
+
+  struct object obj;
+  init(&obj);
+  char* b = malloc(1);
+  *b = 'a'; // good
+  obj.ops->release(b);
+  *b = 'b'; // bad
+  return 0;
+}
@@ -0,0 +1,12 @@
+#include <stdlib.h>
+#include <stdio.h>
+
+int main () {
+  char buf[8];
+  char* out = malloc(8);
+  buf[0] = 'a';
+  out[0] = 'b';
+  printf("good! %c %c\n", buf[0], out[0]);
+  free(out + 1); // bad
+  free(buf); // bad
+}
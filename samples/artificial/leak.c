@@ -0,0 +1,12 @@
+#include <stdlib.h>
+#include <stdio.h>
+
+int main () {
+  char* out = malloc(1);
+  *out = 'a'; // good
+  printf("good! %c\n", *out);
+  out = malloc(1); // bad, first allocation is lost
+  *out = 'b';
+  printf("good! %c\n", *out);
+  free(out);
+}
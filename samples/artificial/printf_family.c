@@ -0,0 +1,15 @@
+#include <stdlib.h>
+#include <stdio.h>
+
+int main (int argc, char** argv) {
+  char buf[16];
+  char* out = malloc(8);
+  out[0] = 'a';
+  out[1] = 0;
+  fprintf(stderr, "%s\n", out); // good
+  free(out);
+  fprintf(stderr, "%d %p\n", argc, out); // good, the pointer is only printed as a number
+  fprintf(stderr, "%s\n", out); // bad
+  snprintf(buf, sizeof(buf), "%d %s\n", argc, out); // bad
+  return buf[0];
+}
@@ -0,0 +1,10 @@
+#include <stdlib.h>
+
+int main () {
+  char* out = malloc(1);
+  *out = 'a'; // good
+  char* bigger = realloc(out, 16);
+  *out = 'b'; // bad
+  *bigger = 'c'; // good
+  free(bigger);
+}
@@ -0,0 +1,18 @@
+#include <stdio.h>
+
+int* slots[8];
+
+// Each level publishes a pointer to its own local, and uses it after the deeper levels return
+void walk(int n) {
+  int local = n;
+  slots[n] = &local;
+  if (n > 0) {
+    walk(n - 1);
+  }
+  *slots[n] += 1; // good
+}
+
+int main (int argc, char** argv) {
+  walk(argc & 7);
+  return 0;
+}
@@ -0,0 +1,20 @@
+#define _GNU_SOURCE
+#include <stdlib.h>
+#include <stdio.h>
+#include <string.h>
+
+int main (int argc, char** argv) {
+  char* copy = strdup(argv[0]);
+  *copy = 'a'; // good
+  free(copy);
+  *copy = 'b'; // bad
+
+  char* formatted;
+  if (asprintf(&formatted, "%d", argc) < 0) {
+    return 1;
+  }
+  *formatted = 'c'; // good
+  free(formatted);
+  *formatted = 'd'; // bad
+  return 0;
+}
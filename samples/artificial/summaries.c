@@ -0,0 +1,16 @@
+#include <stdlib.h>
+#include <string.h>
+
+int main () {
+  char* out = malloc(8);
+  strcpy(out, "good"); // good
+  free(out);
+  size_t len = strlen(out); // bad
+
+  char* other = malloc(8);
+  strcpy(other, "good");
+  char* inner = strchr(other, 'o');
+  free(other);
+  *inner = 'b'; // bad
+  return len;
+}
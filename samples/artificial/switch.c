@@ -0,0 +1,27 @@
+#include <stdlib.h>
+
+int main (int argc, char** argv) {
+  char* out = malloc(1);
+  *out = 'a'; // good
+  switch (argc) {
+    case 1:
+      *out = 'b';
+      break;
+    case 2:
+      *out = 'c';
+      break;
+    case 3:
+      free(out);
+      break;
+    case 4:
+      *out = 'd';
+      break;
+    case 5:
+      *out = 'e';
+      break;
+    default:
+      break;
+  }
+  *out = 'f'; // bad, when argc is 3
+  return 0;
+}
@@ -0,0 +1,49 @@
+#include <stdlib.h>
+
+int main (int argc, char** argv) {
+  char* out = malloc(1);
+  int n = 0;
+  free(out);
+  switch (argc) {
+    case 1:
+      n = 3;
+      break;
+    case 2:
+      n = 5;
+      break;
+    case 3:
+      n = 7;
+      break;
+    case 4:
+      n = 11;
+      break;
+    case 5:
+      n = 13;
+      break;
+    default:
+      break;
+  }
+  out = malloc(1);
+  // This switch's table directly follows the first one
+  switch (argc) {
+    case 1:
+      *out = 'a';
+      break;
+    case 2:
+      *out = 'b';
+      break;
+    case 3:
+      *out = 'c';
+      break;
+    case 4:
+      *out = 'd';
+      break;
+    case 5:
+      *out = 'e';
+      break;
+    default:
+      break;
+  }
+  free(out);
+  return n;
+}
@@ -0,0 +1,14 @@
+#include <stdio.h>
+
+void escape(char** out) {
+  char buf[8];
+  buf[0] = 'a';
+  *out = buf;
+}
+
+int main () {
+  char* out;
+  escape(&out);
+  *out = 'b'; // bad
+  printf("past bad! %c\n", *out);
+}
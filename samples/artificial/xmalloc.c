@@ -0,0 +1,24 @@
+#include <stdlib.h>
+#include <stdio.h>
+
+void* xmalloc(size_t size) {
+  void* out = malloc(size);
+  if (!out) {
+    abort();
+  }
+  return out;
+}
+
+int main () {
+  int* a = xmalloc(sizeof(int));
+  int* b = xmalloc(sizeof(int));
+  free(b);
+  // Ages b's freed object, which shares a name with a's unless heap objects are cloned
+  int* c = xmalloc(sizeof(int));
+  *a = 1; // good
+  *c = 2; // good
+  printf("good! %d %d\n", *a, *c);
+  free(a);
+  free(c);
+  return 0;
+}
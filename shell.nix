@@ -26,9 +26,17 @@ let vim = vim_configurable.customize {
   ];}];
 }; in
 
+# Cross compilers for the samples in samples/artificial built for other platforms
+let crossGcc = [
+  nixpkgs.pkgsCross.gnu32.buildPackages.gcc
+  nixpkgs.pkgsCross.aarch64-multiplatform.buildPackages.gcc
+  nixpkgs.pkgsCross.armv7l-hf-multiplatform.buildPackages.gcc
+  nixpkgs.pkgsCross.mingwW64.buildPackages.gcc
+]; in
+
 stdenv.mkDerivation rec {
   name = "bap-rust";
-  buildInputs = [ rust libbap vim clang rustracer rustc ];
+  buildInputs = [ rust libbap vim clang rustracer rustc ] ++ crossGcc;
   LIBCLANG_PATH = "${llvmPackages.clang-unwrapped.lib}/lib";
 }
 ) {rust = nixpkgs.rustChannels.nightly.rust; }
@@ -0,0 +1,126 @@
+//! alloc_spec describes user provided allocator and deallocator models, for codebases which wrap
+//! or replace the standard allocator.
+//!
+//! Specs are JSON, e.g.
+//!
+//! ```json
+//! {
+//!   "allocators": [
+//!     {"name": "pool_alloc"},
+//!     {"name": "talloc_new_into", "result": {"out_param": 1}}
+//!   ],
+//!   "deallocators": [
+//!     {"name": "xfree", "args": [0]},
+//!     {"name": "*_unref", "args": [0]}
+//!   ]
+//! }
+//! ```
+//!
+//! Names may contain `*`, which matches any run of characters. Functions are matched by the name
+//! of the PLT stub they are called through, and models are used in addition to the builtin ones.
+use crate::datalog::*;
+use crate::error::Error;
+use crate::var::Var;
+use std::path::Path;
+
+/// Where an allocating function places the pointer to its new object
+#[derive(Eq, Copy, Debug, PartialEq, Clone, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AllocResult {
+    /// In the return register
+    Return,
+    /// Stored through the pointer passed as this argument (0-indexed)
+    OutParam(usize),
+}
+
+impl Default for AllocResult {
+    fn default() -> Self {
+        AllocResult::Return
+    }
+}
+
+/// A function which returns a fresh heap object
+#[derive(Eq, Debug, PartialEq, Clone, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub struct Allocator {
+    /// Name pattern of the function
+    pub name: String,
+    /// Where the new object is placed, defaults to the return register
+    #[serde(default)]
+    pub result: AllocResult,
+}
+
+/// A function which frees heap objects passed to it
+#[derive(Eq, Debug, PartialEq, Clone, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub struct Deallocator {
+    /// Name pattern of the function
+    pub name: String,
+    /// Which arguments (0-indexed) are freed
+    pub args: Vec<usize>,
+}
+
+/// A set of allocator and deallocator models
+#[derive(Eq, Debug, PartialEq, Clone, Ord, PartialOrd, Hash, Serialize, Deserialize, Default)]
+pub struct AllocSpec {
+    /// Functions which allocate
+    #[serde(default)]
+    pub allocators: Vec<Allocator>,
+    /// Functions which free
+    #[serde(default)]
+    pub deallocators: Vec<Deallocator>,
+}
+
+impl AllocSpec {
+    /// Reads a JSON spec from disk
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file_name = path.as_ref().display().to_string();
+        let contents = ::std::fs::read(path.as_ref()).map_err(|source| Error::Io {
+            file_name: file_name.clone(),
+            source,
+        })?;
+        ::serde_json::from_slice(&contents).map_err(|source| Error::AllocSpec { file_name, source })
+    }
+}
+
+// Glob match supporting only `*`
+fn name_matches(pattern: &str, name: &str) -> bool {
+    match pattern.find('*') {
+        None => pattern == name,
+        Some(star) => {
+            let (prefix, rest) = (&pattern[..star], &pattern[star + 1..]);
+            if !name.starts_with(prefix) {
+                return false;
+            }
+            let name = &name[prefix.len()..];
+            (0..=name.len())
+                .filter(|i| name.is_char_boundary(*i))
+                .any(|i| name_matches(rest, &name[i..]))
+        }
+    }
+}
+
+// Datalog functions
+
+pub fn is_alloc_model(i: &AllocSpecIsAllocModelIn) -> Vec<AllocSpecIsAllocModelOut> {
+    if name_matches(i.pattern, i.func_name) {
+        vec![AllocSpecIsAllocModelOut {}]
+    } else {
+        Vec::new()
+    }
+}
+
+pub fn is_dealloc_model(i: &AllocSpecIsDeallocModelIn) -> Vec<AllocSpecIsDeallocModelOut> {
+    if name_matches(i.pattern, i.func_name) {
+        vec![AllocSpecIsDeallocModelOut {}]
+    } else {
+        Vec::new()
+    }
+}
+
+pub fn result_uses(i: &AllocSpecResultUsesIn) -> Vec<AllocSpecResultUsesOut> {
+    match *i.result {
+        AllocResult::Return => Vec::new(),
+        AllocResult::OutParam(n) => vec![AllocSpecResultUsesOut {
+            var: Var::arg(n, i.base, i.conv),
+        }],
+    }
+}
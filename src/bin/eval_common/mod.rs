@@ -34,7 +34,13 @@ fn check_mem() -> usize {
 }
 
 pub fn marduk(names: &[String], mode: Config) -> Option<Run> {
-    let mut db = uaf(names, mode);
+    let mut db = match uaf(names, mode) {
+        Ok(db) => db,
+        Err(e) => {
+            eprintln!("{}", e);
+            return None;
+        }
+    };
     let pre = Instant::now();
     let time_limit = Duration::from_secs(crate::TIME_LIMIT);
     while !db.run_rules_once().is_empty() {
@@ -71,12 +71,25 @@ fn measure_bad_juliet() -> Vec<Measurement> {
     out
 }
 
+const ALIAS_MODES: &[Config] = &[
+    Config::CONTEXT_INSENSITIVE,
+    Config::CONTEXT_SENSITIVE,
+    Config {
+        stack_depth: 2,
+        ..Config::CONTEXT_SENSITIVE
+    },
+    Config {
+        stack_depth: 3,
+        ..Config::CONTEXT_SENSITIVE
+    },
+];
+
 fn measure_uaf(names: &[&'static str], expected: &[(u64, u64)]) -> Vec<Measurement> {
     let names: Vec<_> = names
         .iter()
         .map(|x| format!("samples/whole/{}", x))
         .collect();
-    [Config::CONTEXT_INSENSITIVE, Config::CONTEXT_SENSITIVE]
+    ALIAS_MODES
         .iter()
         .flat_map(|mode| measure_mode(&names, *mode, expected))
         .collect()
@@ -1,13 +1,42 @@
 extern crate clap;
 extern crate env_logger;
 extern crate marduk;
+#[macro_use]
+extern crate serde_derive;
+
+mod report;
 
 use marduk::Config;
 
-fn print_results(db: &mut marduk::Database) {
-    println!("UaF (free -> use):");
-    for x in db.query_uaf_flow() {
-        println!("{}", x);
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Format {
+    Text,
+    Json,
+    Sarif,
+}
+
+fn print_results(db: &mut marduk::Database, config: Config, format: Format, witness: bool) {
+    let findings = report::findings(db, config.uses_ctx(), witness);
+    match format {
+        Format::Text => {
+            for &(kind, _, heading) in report::RULES {
+                if kind == "leak" && !config.detects_leaks() {
+                    continue;
+                }
+                println!("{}:", heading);
+                for x in findings.iter().filter(|x| x.kind == kind) {
+                    println!("{}", x);
+                }
+            }
+        }
+        Format::Json => println!("{}", report::to_json(&findings)),
+        Format::Sarif => println!("{}", report::to_sarif(&findings)),
+    }
+}
+
+fn print_diagnostics(db: &mut marduk::Database) {
+    for x in db.query_load_diagnostic() {
+        eprintln!("warning: {}", x);
     }
 }
 
@@ -78,7 +107,7 @@ fn main() {
         .author("Matthew Maurer")
         .arg(
             Arg::with_name("INPUTS")
-                .help("Which ELF files to load")
+                .help("Which ELF or PE files to load")
                 .multiple(true)
                 .required(true)
                 .index(1),
@@ -99,6 +128,64 @@ fn main() {
                 .short("s")
                 .long("sensitivity"),
         )
+        .arg(
+            Arg::with_name("stack-depth")
+                .help(
+                    "Number of return sites to keep in the callstack for context \
+                     sensitive analysis. Defaults to 1.",
+                )
+                .takes_value(true)
+                .short("k")
+                .long("stack-depth"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .help(
+                    "Output format for findings. \"json\" and \"sarif\" are intended \
+                     for machine consumption. Defaults to \"text\".",
+                )
+                .takes_value(true)
+                .possible_values(&["text", "json", "sarif"])
+                .short("f")
+                .long("format"),
+        )
+        .arg(
+            Arg::with_name("witness")
+                .help(
+                    "Reconstruct an instruction path from the free to the use for \
+                     each finding, listing which variables point to the freed \
+                     object along the way.",
+                )
+                .short("w")
+                .long("witness"),
+        )
+        .arg(
+            Arg::with_name("leaks")
+                .help(
+                    "Also report heap allocations which become unreachable \
+                     without having been freed.",
+                )
+                .short("l")
+                .long("leaks"),
+        )
+        .arg(
+            Arg::with_name("heap-clone")
+                .help(
+                    "Name heap objects returned by allocation wrappers after the \
+                     call they return to, so that objects allocated through a \
+                     wrapper on behalf of different callers are kept apart.",
+                )
+                .long("heap-clone"),
+        )
+        .arg(
+            Arg::with_name("alloc-spec")
+                .help(
+                    "JSON file describing additional allocating and freeing \
+                     functions, e.g. custom pool allocators.",
+                )
+                .takes_value(true)
+                .long("alloc-spec"),
+        )
         .arg(
             Arg::with_name("undefined-initialize")
                 .help(
@@ -134,7 +221,30 @@ fn main() {
         s => panic!("Unknown sensitivity: {}", s),
     };
 
+    if let Some(depth) = args.value_of("stack-depth") {
+        config.stack_depth = depth
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid stack depth: {}", depth));
+    }
     config.undef_hack = args.is_present("undefined-initialize");
+    config.leaks = args.is_present("leaks");
+    config.heap_clone = args.is_present("heap-clone");
+    if let Some(path) = args.value_of("alloc-spec") {
+        match marduk::AllocSpec::from_file(path) {
+            Ok(spec) => config.alloc_spec = Some(Box::leak(Box::new(spec))),
+            Err(e) => {
+                eprintln!("{}", e);
+                ::std::process::exit(1);
+            }
+        }
+    }
+
+    let format = match args.value_of("format").unwrap_or("text") {
+        "text" => Format::Text,
+        "json" => Format::Json,
+        "sarif" => Format::Sarif,
+        s => panic!("Unknown format: {}", s),
+    };
 
     let files: Vec<String> = args
         .values_of("INPUTS")
@@ -145,15 +255,30 @@ fn main() {
     run_marduk(
         &files,
         config,
+        format,
+        args.is_present("witness"),
         args.is_present("progress"),
         args.is_present("debug"),
     );
 }
 
-fn run_marduk(files: &[String], config: Config, progress: bool, debug: bool) {
+fn run_marduk(
+    files: &[String],
+    config: Config,
+    format: Format,
+    witness: bool,
+    progress: bool,
+    debug: bool,
+) {
     use std::time::Instant;
 
-    let mut db = marduk::uaf(files, config);
+    let mut db = match marduk::uaf(files, config) {
+        Ok(db) => db,
+        Err(e) => {
+            eprintln!("{}", e);
+            ::std::process::exit(1);
+        }
+    };
 
     let mut step = 0;
     let mut last_round = Vec::new();
@@ -174,7 +299,8 @@ fn run_marduk(files: &[String], config: Config, progress: bool, debug: bool) {
             );
         }
     }
-    print_results(&mut db);
+    print_diagnostics(&mut db);
+    print_results(&mut db, config, format, witness);
     if debug {
         print_state(&mut db);
     }
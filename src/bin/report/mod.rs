@@ -0,0 +1,548 @@
+//! Machine readable renderings of marduk's findings, for consumption by CI and code scanning
+//! dashboards.
+extern crate marduk;
+extern crate serde_json;
+
+use self::marduk::{Database, Witnesses};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter, Result};
+
+const SARIF_VERSION: &str = "2.1.0";
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const TOOL_URI: &str = "https://github.com/maurer/marduk";
+
+#[derive(Serialize)]
+pub struct Site {
+    pub file: String,
+    pub addr: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disassembly: Option<String>,
+    // Return addresses, innermost first. Only present in context sensitive mode.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub context: Vec<u64>,
+}
+
+#[derive(Serialize)]
+pub struct PathStep {
+    pub edge: String,
+    pub site: Site,
+    // Variables pointing to the freed object on entry to this step
+    pub dangling: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct Finding {
+    pub kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub var: Option<String>,
+    // The object passed to free, for findings about the free itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object: Option<String>,
+    pub alloc: Vec<Site>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub free: Option<Site>,
+    // The return which popped the frame a pointer pointed into
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ret: Option<Site>,
+    #[serde(rename = "use", skip_serializing_if = "Option::is_none")]
+    pub use_: Option<Site>,
+    // Where the last reference to a leaked object was dropped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lost: Option<Site>,
+    // Instructions from free to use. Only present when witness paths were requested.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub path: Vec<PathStep>,
+}
+
+struct Symbols {
+    // (file, addr) -> (function name, offset)
+    syms: BTreeMap<(String, u64), (String, u64)>,
+    disasms: BTreeMap<(String, u64), String>,
+}
+
+impl Symbols {
+    fn new(db: &mut Database) -> Self {
+        let mut syms = BTreeMap::new();
+        for sym in db.query_loc_sym() {
+            syms.insert(
+                (sym.loc.file_name.to_string(), sym.loc.addr),
+                (sym.name, sym.offset),
+            );
+        }
+        let mut disasms = BTreeMap::new();
+        for lift in db.query_get_disasms() {
+            disasms.insert((lift.loc.file_name.to_string(), lift.loc.addr), lift.disasm);
+        }
+        Symbols { syms, disasms }
+    }
+
+    fn site(&self, file: String, addr: u64, context: Vec<u64>) -> Site {
+        let key = (file, addr);
+        let sym = self.syms.get(&key);
+        Site {
+            symbol: sym.map(|&(ref name, _)| name.clone()),
+            offset: sym.map(|&(_, offset)| offset),
+            disassembly: self.disasms.get(&key).cloned(),
+            context,
+            file: key.0,
+            addr,
+        }
+    }
+}
+
+// Loc is not nameable outside of marduk, so we pull it apart here rather than in a function
+macro_rules! site {
+    ($symbols:expr, $loc:expr) => {
+        $symbols.site(
+            $loc.file_name.to_string(),
+            $loc.addr,
+            $loc.call_string().iter().map(|ret| ret.addr).collect(),
+        )
+    };
+}
+
+pub fn findings(db: &mut Database, context: bool, witness: bool) -> Vec<Finding> {
+    let symbols = Symbols::new(db);
+    let witnesses = if witness {
+        Some(Witnesses::new(db))
+    } else {
+        None
+    };
+    let mut allocs = BTreeMap::new();
+    for x in db.query_uaf_witness() {
+        allocs
+            .entry((x.var, x.free, x.use_))
+            .or_insert_with(Vec::new)
+            .push(x.alloc);
+    }
+    // In context sensitive mode, only the use-after-frees context_flow keeps are reported
+    let in_context: Option<BTreeSet<_>> = if context {
+        Some(
+            db.query_context_flow()
+                .into_iter()
+                .map(|uaf| (uaf.free, uaf.use_))
+                .collect(),
+        )
+    } else {
+        None
+    };
+    // Double frees are reported the same way as a use-after-free, with the second free as the use
+    let uafs = db
+        .query_uaf_flow()
+        .into_iter()
+        .filter(|uaf| {
+            in_context.as_ref().map_or(true, |flows| {
+                flows.contains(&(uaf.free.clone(), uaf.use_.clone()))
+            })
+        })
+        .map(|uaf| ("use-after-free", uaf.var, uaf.free, uaf.use_));
+    let double_frees = db
+        .query_double_free()
+        .into_iter()
+        .filter(|df| !context || df.free2.is_stacked())
+        .map(|df| ("double-free", df.var, df.free, df.free2));
+    let mut out: Vec<_> = uafs
+        .chain(double_frees)
+        .map(|(kind, var, free, use_)| Finding {
+            kind,
+            var: Some(var.to_string()),
+            object: None,
+            alloc: allocs
+                .get(&(var.clone(), free.clone(), use_.clone()))
+                .into_iter()
+                .flat_map(|sites| sites.iter().map(|site| site!(symbols, site)))
+                .collect(),
+            path: witnesses
+                .iter()
+                .flat_map(|w| w.path(&free, &use_))
+                .flatten()
+                .map(|step| PathStep {
+                    edge: step.edge.to_string(),
+                    site: site!(symbols, step.loc),
+                    dangling: step.dangling.iter().map(|v| v.to_string()).collect(),
+                })
+                .collect(),
+            free: Some(site!(symbols, free)),
+            ret: None,
+            use_: Some(site!(symbols, use_)),
+            lost: None,
+        })
+        .collect();
+    out.extend(
+        db.query_invalid_free()
+            .into_iter()
+            .filter(|inv| !context || inv.free.is_stacked())
+            .map(|inv| Finding {
+                kind: "invalid-free",
+                var: Some(inv.var.to_string()),
+                object: Some(inv.object.to_string()),
+                alloc: Vec::new(),
+                free: Some(site!(symbols, inv.free)),
+                ret: None,
+                use_: None,
+                lost: None,
+                path: Vec::new(),
+            }),
+    );
+    out.extend(
+        db.query_use_after_return()
+            .into_iter()
+            .filter(|uar| !context || uar.use_.is_stacked())
+            .map(|uar| Finding {
+                kind: "use-after-return",
+                var: Some(uar.var.to_string()),
+                object: None,
+                alloc: Vec::new(),
+                free: None,
+                ret: Some(site!(symbols, uar.ret)),
+                use_: Some(site!(symbols, uar.use_)),
+                lost: None,
+                path: Vec::new(),
+            }),
+    );
+    out.extend(
+        db.query_leak()
+            .into_iter()
+            .filter(|leak| !context || leak.lost.is_stacked())
+            .map(|leak| Finding {
+                kind: "leak",
+                var: None,
+                object: None,
+                alloc: vec![site!(symbols, leak.alloc)],
+                free: None,
+                ret: None,
+                use_: None,
+                lost: Some(site!(symbols, leak.lost)),
+                path: Vec::new(),
+            }),
+    );
+    out
+}
+
+impl Display for Site {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match (&self.symbol, self.offset) {
+            (&Some(ref symbol), Some(offset)) => write!(f, "{}+0x{:x}", symbol, offset)?,
+            _ => write!(f, "0x{:x}", self.addr)?,
+        }
+        write!(f, " ({})", self.file)
+    }
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        let var = self.var.as_ref().map_or("?", |var| var.as_str());
+        match (&self.free, &self.use_, &self.lost) {
+            (&Some(ref free), &Some(ref use_), _) => write!(f, "{} -> {} via {}", free, use_, var)?,
+            (&Some(ref free), &None, _) => write!(
+                f,
+                "{} frees {} via {}",
+                free,
+                self.object.as_ref().map_or("?", |obj| obj.as_str()),
+                var
+            )?,
+            (&None, &Some(ref use_), _) => write!(
+                f,
+                "{} -> {} via {}",
+                self.ret
+                    .as_ref()
+                    .map_or("?".to_string(), |ret| ret.to_string()),
+                use_,
+                var
+            )?,
+            (_, _, &Some(ref lost)) => write!(f, "lost at {}", lost)?,
+            _ => (),
+        }
+        for alloc in &self.alloc {
+            write!(f, "\n\tallocated at {}", alloc)?;
+        }
+        for step in &self.path {
+            write!(f, "\n\t{} {}", step.edge, step.site)?;
+            if !step.dangling.is_empty() {
+                write!(f, " [{}]", step.dangling.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn to_json(findings: &[Finding]) -> String {
+    #[derive(Serialize)]
+    struct Report<'a> {
+        findings: &'a [Finding],
+    }
+    serde_json::to_string_pretty(&Report { findings }).unwrap()
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifLog {
+    version: &'static str,
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    runs: Vec<SarifRun>,
+}
+
+#[derive(Serialize)]
+struct SarifRun {
+    tool: SarifTool,
+    results: Vec<SarifResult>,
+}
+
+#[derive(Serialize)]
+struct SarifTool {
+    driver: SarifDriver,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifDriver {
+    name: &'static str,
+    information_uri: &'static str,
+    rules: Vec<SarifRule>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifRule {
+    id: &'static str,
+    short_description: SarifMessage,
+}
+
+#[derive(Serialize)]
+struct SarifMessage {
+    text: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    rule_id: &'static str,
+    level: &'static str,
+    message: SarifMessage,
+    locations: Vec<SarifLocation>,
+    related_locations: Vec<SarifLocation>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    code_flows: Vec<SarifCodeFlow>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifCodeFlow {
+    thread_flows: Vec<SarifThreadFlow>,
+}
+
+#[derive(Serialize)]
+struct SarifThreadFlow {
+    locations: Vec<SarifThreadFlowLocation>,
+}
+
+#[derive(Serialize)]
+struct SarifThreadFlowLocation {
+    location: SarifLocation,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifLocation {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<usize>,
+    physical_location: SarifPhysicalLocation,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    logical_locations: Vec<SarifLogicalLocation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<SarifMessage>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifPhysicalLocation {
+    artifact_location: SarifArtifactLocation,
+    address: SarifAddress,
+}
+
+#[derive(Serialize)]
+struct SarifArtifactLocation {
+    uri: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifAddress {
+    absolute_address: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifLogicalLocation {
+    fully_qualified_name: String,
+    kind: &'static str,
+}
+
+impl Site {
+    fn sarif(&self, id: Option<usize>, message: Option<String>) -> SarifLocation {
+        SarifLocation {
+            id,
+            physical_location: SarifPhysicalLocation {
+                artifact_location: SarifArtifactLocation {
+                    uri: self.file.clone(),
+                },
+                address: SarifAddress {
+                    absolute_address: self.addr,
+                },
+            },
+            logical_locations: self
+                .symbol
+                .iter()
+                .map(|symbol| SarifLogicalLocation {
+                    fully_qualified_name: format!("{}+0x{:x}", symbol, self.offset.unwrap_or(0)),
+                    kind: "function",
+                })
+                .collect(),
+            message: message.map(|text| SarifMessage { text }),
+        }
+    }
+}
+
+/// (id, description, heading in text output) for each kind of finding
+pub const RULES: &[(&str, &str, &str)] = &[
+    (
+        "use-after-free",
+        "Use of memory after it has been freed",
+        "UaF (free -> use)",
+    ),
+    (
+        "double-free",
+        "Memory freed more than once",
+        "Double free (free -> free)",
+    ),
+    (
+        "invalid-free",
+        "Free of stack memory or of a pointer into the middle of an allocation",
+        "Invalid free",
+    ),
+    (
+        "use-after-return",
+        "Use of stack memory after the function owning it has returned",
+        "UaR (return -> use)",
+    ),
+    (
+        "leak",
+        "Heap memory which becomes unreachable without being freed",
+        "Leak (alloc -> lost)",
+    ),
+];
+
+impl Finding {
+    // The site a finding is reported at
+    fn location(&self) -> &Site {
+        self.use_
+            .as_ref()
+            .or_else(|| self.free.as_ref())
+            .or_else(|| self.lost.as_ref())
+            .expect("finding has no location")
+    }
+
+    fn message(&self) -> String {
+        let var = self.var.as_ref().map_or("?", |var| var.as_str());
+        let object = self.object.as_ref().map_or("?", |obj| obj.as_str());
+        match (self.kind, &self.free, &self.use_) {
+            ("double-free", &Some(ref free), &Some(ref use_)) => format!(
+                "{} is freed at {} after already being freed at {}",
+                var, use_, free
+            ),
+            (_, &Some(ref free), &Some(ref use_)) => {
+                format!("{} is used at {} after being freed at {}", var, use_, free)
+            }
+            (_, &Some(ref free), &None) => format!(
+                "{} is passed to free at {}, but points to {}",
+                var, free, object
+            ),
+            (_, &None, &Some(ref use_)) => format!(
+                "{} is used at {} after its stack frame was popped by the return at {}",
+                var,
+                use_,
+                self.ret
+                    .as_ref()
+                    .map_or("?".to_string(), |ret| ret.to_string())
+            ),
+            (_, &None, &None) => format!(
+                "Memory allocated at {} is lost at {}",
+                self.alloc
+                    .first()
+                    .map_or("?".to_string(), |alloc| alloc.to_string()),
+                self.location()
+            ),
+        }
+    }
+}
+
+pub fn to_sarif(findings: &[Finding]) -> String {
+    let results = findings
+        .iter()
+        .map(|finding| SarifResult {
+            rule_id: finding.kind,
+            level: "error",
+            message: SarifMessage {
+                text: finding.message(),
+            },
+            locations: vec![finding.location().sarif(None, None)],
+            related_locations: finding
+                .use_
+                .iter()
+                .flat_map(|_| {
+                    finding
+                        .free
+                        .iter()
+                        .map(|free| (free, "freed here"))
+                        .chain(finding.ret.iter().map(|ret| (ret, "frame popped here")))
+                })
+                .chain(finding.alloc.iter().map(|alloc| (alloc, "allocated here")))
+                .enumerate()
+                .map(|(i, (site, message))| site.sarif(Some(i + 1), Some(message.to_string())))
+                .collect(),
+            code_flows: if finding.path.is_empty() {
+                Vec::new()
+            } else {
+                vec![SarifCodeFlow {
+                    thread_flows: vec![SarifThreadFlow {
+                        locations: finding
+                            .path
+                            .iter()
+                            .map(|step| SarifThreadFlowLocation {
+                                location: step.site.sarif(None, Some(step.edge.clone())),
+                            })
+                            .collect(),
+                    }],
+                }]
+            },
+        })
+        .collect();
+    let log = SarifLog {
+        version: SARIF_VERSION,
+        schema: SARIF_SCHEMA,
+        runs: vec![SarifRun {
+            tool: SarifTool {
+                driver: SarifDriver {
+                    name: "marduk",
+                    information_uri: TOOL_URI,
+                    rules: RULES
+                        .iter()
+                        .map(|&(id, description, _)| SarifRule {
+                            id,
+                            short_description: SarifMessage {
+                                text: description.to_string(),
+                            },
+                        })
+                        .collect(),
+                },
+            },
+            results,
+        }],
+    };
+    serde_json::to_string_pretty(&log).unwrap()
+}
@@ -12,7 +12,18 @@ use marduk::Config;
 pub const MEMORY_LIMIT: usize = 0;
 pub const TIME_LIMIT: u64 = 0;
 
-const ALIAS_MODES: &[Config] = &[Config::CONTEXT_INSENSITIVE, Config::CONTEXT_SENSITIVE];
+const ALIAS_MODES: &[Config] = &[
+    Config::CONTEXT_INSENSITIVE,
+    Config::CONTEXT_SENSITIVE,
+    Config {
+        stack_depth: 2,
+        ..Config::CONTEXT_SENSITIVE
+    },
+    Config {
+        stack_depth: 3,
+        ..Config::CONTEXT_SENSITIVE
+    },
+];
 
 fn main() {
     use std::fs::File;
@@ -1,7 +1,9 @@
 use super::generation;
 use super::{Constraint, VarPath};
+use crate::alloc_spec::AllocResult;
 use crate::datalog::*;
-use crate::regs::{ARGS, RET_REG};
+use crate::interned_string::InternedString;
+use crate::load::{Loc, Stack};
 use crate::var::Var;
 
 pub fn gen_constraints(i: &ConstraintsGenConstraintsIn) -> Vec<ConstraintsGenConstraintsOut> {
@@ -9,18 +11,48 @@ pub fn gen_constraints(i: &ConstraintsGenConstraintsIn) -> Vec<ConstraintsGenCon
         c: if i.is_call {
             Vec::new()
         } else {
-            generation::extract_constraints(i.bil, i.loc, i.base)
+            let globals = generation::Globals {
+                rip: Some(i.fallthrough.addr),
+                ranges: i.ranges,
+                rodata: i.rodata,
+                code: i.code,
+            };
+            generation::extract_constraints(i.bil, i.loc, i.base, globals)
         },
     }]
 }
 
+fn segment_loc(file_name: &str, start: u64) -> Loc {
+    Loc {
+        file_name: InternedString::from_string(file_name),
+        addr: start,
+        stack: Stack::NoStack,
+    }
+}
+
+pub fn segment_range(i: &ConstraintsSegmentRangeIn) -> Vec<ConstraintsSegmentRangeOut> {
+    vec![ConstraintsSegmentRangeOut {
+        ranges: vec![(segment_loc(i.file_name, *i.start), *i.end)],
+    }]
+}
+
+pub fn segment_image(i: &ConstraintsSegmentImageIn) -> Vec<ConstraintsSegmentImageOut> {
+    vec![ConstraintsSegmentImageOut {
+        segments: vec![(segment_loc(i.file_name, *i.start), i.contents.clone())],
+    }]
+}
+
 pub fn malloc_constraint(i: &ConstraintsMallocConstraintIn) -> Vec<ConstraintsMallocConstraintOut> {
     vec![ConstraintsMallocConstraintOut {
         c: vec![Constraint {
-            lhs: VarPath::reg(RET_REG),
+            lhs: match *i.result {
+                AllocResult::Return => VarPath::reg(i.conv.ret),
+                AllocResult::OutParam(n) => VarPath::var(Var::arg(n, i.base, i.conv)).deref(),
+            },
             rhss: vec![VarPath {
                 base: Var::Alloc {
                     site: i.loc.clone(),
+                    clone: Vec::new(),
                     stale: false,
                 },
                 offsets: vec![Some(0)],
@@ -34,7 +66,7 @@ pub fn free_constraint(i: &ConstraintsFreeConstraintIn) -> Vec<ConstraintsFreeCo
         c: i.args
             .iter()
             .map(|arg_n| Constraint {
-                lhs: VarPath::reg(ARGS[*arg_n]).deref(),
+                lhs: VarPath::var(Var::arg(*arg_n, i.base, i.conv)).deref(),
                 rhss: vec![VarPath {
                     base: Var::Freed {
                         site: i.loc.clone(),
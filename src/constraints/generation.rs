@@ -1,6 +1,6 @@
 use super::{Constraint, VarPath};
-use crate::load::Loc;
-use crate::regs::Reg;
+use crate::load::{Loc, Stack};
+use crate::regs::{self, Reg};
 use crate::var::Var;
 use bap::high::bil;
 use bap::high::bil::Statement;
@@ -60,11 +60,65 @@ pub enum E {
     Const(u64),
 }
 
+/// What constant addresses refer to at an instruction
+#[derive(Clone, Copy)]
+pub struct Globals<'a> {
+    /// Value of RIP, the address of the following instruction, if known
+    pub rip: Option<u64>,
+    /// Writable data segments, as (start, last address)
+    pub ranges: &'a [(Loc, u64)],
+    /// Read-only data segments, as (start, last address)
+    pub rodata: &'a [(Loc, u64)],
+    /// Executable segments, as (start, last address)
+    pub code: &'a [(Loc, u64)],
+}
+
+impl Globals<'static> {
+    /// Leaves RIP unresolved and all constants as numbers
+    pub const NONE: Self = Globals {
+        rip: None,
+        ranges: &[],
+        rodata: &[],
+        code: &[],
+    };
+}
+
+fn in_range(k: u64, cur_addr: &Loc, ranges: &[(Loc, u64)]) -> Option<Loc> {
+    ranges
+        .iter()
+        .find(|&&(ref start, end)| {
+            start.file_name == cur_addr.file_name && start.addr <= k && k <= end
+        })
+        .map(|&(ref start, _)| start.clone())
+}
+
+// Addresses inside a data segment refer to a global variable, and addresses inside an executable
+// one to a function. Other constants are just numbers.
+fn global_or_const(k: u64, cur_addr: &Loc, globals: Globals) -> E {
+    if let Some(start) =
+        in_range(k, cur_addr, globals.ranges).or_else(|| in_range(k, cur_addr, globals.rodata))
+    {
+        let offset = k - start.addr;
+        return E::VP(VarPath::addr(Var::Global { addr: start }).plus(offset));
+    }
+    if in_range(k, cur_addr, globals.code).is_some() {
+        return E::VP(VarPath::addr(Var::Code {
+            addr: Loc {
+                file_name: cur_addr.file_name,
+                addr: k,
+                stack: Stack::NoStack,
+            },
+        }));
+    }
+    E::Const(k)
+}
+
 pub fn extract_expr(
     e: &bil::Expression,
     cur_addr: &Loc,
     func_addr: &Loc,
     tmp_db: &BTreeMap<Var, u64>,
+    globals: Globals,
 ) -> Vec<E> {
     use bap::high::bil::Expression as BE;
     use num_traits::ToPrimitive;
@@ -74,7 +128,12 @@ pub fn extract_expr(
             if bv.type_ == bil::Type::Immediate(1) {
                 return Vec::new();
             }
-            let vp = if bv.name == "RSP" {
+            if bv.name == "RIP" {
+                if let Some(rip) = globals.rip {
+                    return vec![E::Const(rip)];
+                }
+            }
+            let vp = if regs::is_stack_pointer(&bv.name) {
                 VarPath::stack_addr(func_addr, 0)
             } else {
                 match Reg::from_str(bv.name.as_str()) {
@@ -90,16 +149,16 @@ pub fn extract_expr(
                 }
             };
             if let Some(k) = tmp_db.get(&vp.base) {
-                vec![E::Const(*k)]
+                vec![global_or_const(*k, cur_addr, globals)]
             } else {
                 vec![E::VP(vp)]
             }
         }
         BE::Const(ref e) => match e.to_u64() {
-            Some(k) => vec![E::Const(k)],
+            Some(k) => vec![global_or_const(k, cur_addr, globals)],
             None => Vec::new(),
         },
-        BE::Load { ref index, .. } => extract_expr(index, cur_addr, func_addr, tmp_db)
+        BE::Load { ref index, .. } => extract_expr(index, cur_addr, func_addr, tmp_db, globals)
             .into_iter()
             .flat_map(|e| match e {
                 E::VP(v) => vec![E::VP(v.deref())],
@@ -119,7 +178,7 @@ pub fn extract_expr(
             if op == bil::BinOp::Add {
                 // Check for stack-relative addressing
                 if let BE::Var(ref lv) = **lhs {
-                    if lv.name == "RSP" {
+                    if regs::is_stack_pointer(&lv.name) {
                         if let BE::Const(ref bv) = **rhs {
                             return vec![E::VP(VarPath::stack_addr(
                                 func_addr,
@@ -128,7 +187,7 @@ pub fn extract_expr(
                         }
                     }
                 } else if let BE::Var(ref rv) = **rhs {
-                    if rv.name == "RSP" {
+                    if regs::is_stack_pointer(&rv.name) {
                         if let BE::Const(ref bv) = **lhs {
                             return vec![E::VP(VarPath::stack_addr(
                                 func_addr,
@@ -138,8 +197,8 @@ pub fn extract_expr(
                     }
                 }
                 // Since we don't have stack relative addressing, it's time to do field math
-                let lhe = extract_expr(lhs, cur_addr, func_addr, tmp_db);
-                let rhe = extract_expr(rhs, cur_addr, func_addr, tmp_db);
+                let lhe = extract_expr(lhs, cur_addr, func_addr, tmp_db, globals);
+                let rhe = extract_expr(rhs, cur_addr, func_addr, tmp_db, globals);
                 let mut out = Vec::new();
                 for e0 in &lhe {
                     for e1 in &rhe {
@@ -148,7 +207,9 @@ pub fn extract_expr(
                             | (&E::VP(ref v), &E::Const(ref k)) => out.push(E::VP(v.plus(*k))),
                             // TODO this is a little iffy, doesn't do bitwidth right
                             // Unlikely to be a problem with pointers though
-                            (&E::Const(ref k), &E::Const(ref k2)) => out.push(E::Const(*k + *k2)),
+                            (&E::Const(ref k), &E::Const(ref k2)) => {
+                                out.push(global_or_const(*k + *k2, cur_addr, globals))
+                            }
                             (&E::VP(ref v), &E::VP(ref v2)) => {
                                 out.push(E::VP(v.unknown()));
                                 out.push(E::VP(v2.unknown()));
@@ -164,8 +225,8 @@ pub fn extract_expr(
                 // Just enumerate everything on the left, everything on the right, set their offset
                 // to None for "who knows", and return. This is equivalent to the old field
                 // insensitive code
-                let mut used: Vec<E> = extract_expr(lhs, cur_addr, func_addr, tmp_db);
-                used.extend(extract_expr(rhs, cur_addr, func_addr, tmp_db));
+                let mut used: Vec<E> = extract_expr(lhs, cur_addr, func_addr, tmp_db, globals);
+                used.extend(extract_expr(rhs, cur_addr, func_addr, tmp_db, globals));
                 let mut out = BTreeSet::new();
                 for e in used {
                     match e {
@@ -183,12 +244,12 @@ pub fn extract_expr(
             false_expr: ref rhs,
             ..
         } => {
-            let mut out = extract_expr(&*lhs, cur_addr, func_addr, tmp_db);
-            out.extend(extract_expr(&*rhs, cur_addr, func_addr, tmp_db));
+            let mut out = extract_expr(&*lhs, cur_addr, func_addr, tmp_db, globals);
+            out.extend(extract_expr(&*rhs, cur_addr, func_addr, tmp_db, globals));
             out
         }
         BE::Let { .. } => panic!("let unimpl"),
-        BE::Cast { ref arg, .. } => extract_expr(arg, cur_addr, func_addr, tmp_db),
+        BE::Cast { ref arg, .. } => extract_expr(arg, cur_addr, func_addr, tmp_db, globals),
         BE::Unknown { .. } | BE::UnOp { .. } | BE::Extract { .. } | BE::Concat { .. } => Vec::new(),
     }
 }
@@ -213,8 +274,10 @@ fn extract_move_var(
             } else {
                 panic!("Writing to memory, but the expression isn't a store")
             };
-            let lhs_vars = extract_expr(index, cur_addr, func_addr, &tmp_db);
-            let rhs_vars = extract_expr(rhs, cur_addr, func_addr, &tmp_db);
+            // Globals are left as constants here, so a store into one isn't mistaken for a use of
+            // the pointer it held
+            let lhs_vars = extract_expr(index, cur_addr, func_addr, &tmp_db, Globals::NONE);
+            let rhs_vars = extract_expr(rhs, cur_addr, func_addr, &tmp_db, Globals::NONE);
             let mut out = Vec::new();
             for lhs_evar in lhs_vars {
                 if let VP(l) = lhs_evar {
@@ -232,7 +295,7 @@ fn extract_move_var(
         }
         bil::Type::Immediate(_) => {
             let mut out = Vec::new();
-            for eval in extract_expr(rhs, cur_addr, func_addr, &tmp_db) {
+            for eval in extract_expr(rhs, cur_addr, func_addr, &tmp_db, Globals::NONE) {
                 match eval {
                     E::VP(v) => {
                         if v.derefs() > 2 {
@@ -253,6 +316,7 @@ fn extract_move(
     cur_addr: &Loc,
     func_addr: &Loc,
     tmp_db: &mut BTreeMap<Var, u64>,
+    globals: Globals,
 ) -> Vec<Constraint> {
     match lhs.type_ {
         bil::Type::Memory { .. } => {
@@ -266,8 +330,8 @@ fn extract_move(
             } else {
                 panic!("Writing to memory, but the expression isn't a store")
             };
-            let lhs_vars = extract_expr(index, cur_addr, func_addr, &tmp_db);
-            let rhs_vars = extract_expr(value, cur_addr, func_addr, &tmp_db);
+            let lhs_vars = extract_expr(index, cur_addr, func_addr, &tmp_db, globals);
+            let rhs_vars = extract_expr(value, cur_addr, func_addr, &tmp_db, globals);
             let mut out = Vec::new();
             for lhs_evar in lhs_vars {
                 let lhs_expr = match &lhs_evar {
@@ -309,7 +373,7 @@ fn extract_move(
         bil::Type::Immediate(_) => {
             let lv = if lhs.tmp {
                 Var::temp(lhs.name.as_str())
-            } else if lhs.name == "RSP" {
+            } else if regs::is_stack_pointer(&lhs.name) {
                 // Suppress generation of RSP constraints - we're handling stack discipline
                 // separately
                 return Vec::new();
@@ -320,7 +384,7 @@ fn extract_move(
                 return Vec::new();
             };
             let mut ks = Vec::new();
-            let rhs_exprs: Vec<_> = extract_expr(rhs, cur_addr, func_addr, &tmp_db)
+            let rhs_exprs: Vec<_> = extract_expr(rhs, cur_addr, func_addr, &tmp_db, globals)
                 .into_iter()
                 .filter_map(|eval| match eval {
                     E::VP(vp) => Some(vp),
@@ -351,11 +415,23 @@ fn extract_move(
     }
 }
 
-pub fn extract_constraints(sema: &[Statement], cur: &Loc, func_loc: &Loc) -> Vec<Constraint> {
+pub fn extract_constraints(
+    sema: &[Statement],
+    cur: &Loc,
+    func_loc: &Loc,
+    globals: Globals,
+) -> Vec<Constraint> {
     let mut constraints = Vec::new();
     let mut tmp_db: BTreeMap<Var, u64> = BTreeMap::new();
+    let extract = |lhs: &bil::Variable,
+                   rhs: &bil::Expression,
+                   cur_addr: &Loc,
+                   func_addr: &Loc,
+                   tmp_db: &mut BTreeMap<Var, u64>| {
+        extract_move(lhs, rhs, cur_addr, func_addr, tmp_db, globals)
+    };
     for stmt in sema {
-        constraints.extend(move_walk(stmt, cur, func_loc, &extract_move, &mut tmp_db));
+        constraints.extend(move_walk(stmt, cur, func_loc, &extract, &mut tmp_db));
     }
     constraints
 }
@@ -1,10 +1,11 @@
+use crate::alloc_spec::AllocResult;
 use crate::constraints::Constraint;
 use crate::var::Var;
 use bap::basic::Arch;
 use bap::high::bil::Statement;
 use std::collections::BTreeSet;
 
-use crate::regs::{Reg, ARGS};
+use crate::regs::{CallConv, Reg};
 type Bytes = Vec<u8>;
 type Sema = Vec<Statement>;
 type StringSet = BTreeSet<String>;
@@ -13,18 +14,20 @@ type Constraints = Vec<Constraint>;
 type LocSet = Vec<Loc>;
 type Vusize = Vec<usize>;
 type Vars = Vec<Var>;
+type MaybeAddr = Option<u64>;
+type Ranges = Vec<(Loc, u64)>;
+type Images = Vec<(Loc, Bytes)>;
+type ArchResult = Result<Arch, String>;
+type Pads = Result<Vec<(u64, String)>, String>;
 use crate::effect::Effect;
 use crate::load::Loc;
-use crate::points_to::PointsTo;
+use crate::points_to::{PointsTo, VarRef};
 use crate::use_def::KillSpec;
 
 use crate::constraints::datalog as constraints;
 use crate::context;
 use crate::live;
 
-const VAR_ARG_0: Var = Var::Register { register: ARGS[0] };
-const VAR_ARG_1: Var = Var::Register { register: ARGS[1] };
-
 fn effect_merge(efs: &[&Effect]) -> Effect {
     let mut out = efs[0].clone();
     for eff in &efs[1..] {
@@ -78,7 +81,9 @@ mycroft_files!(
     "mycroft/defs.my",
     "mycroft/undef_entry.my",
     "mycroft/fmt_str.my",
+    "mycroft/summaries.my",
     "mycroft/constraints.my",
+    "mycroft/jump_table.my",
     "mycroft/flow.my",
     "mycroft/uaf.my",
     "mycroft/fun_effect.my",
@@ -1,5 +1,6 @@
 use crate::datalog::*;
 use crate::load::Loc;
+use crate::points_to::PointsTo;
 use std::collections::BTreeSet;
 
 #[derive(Debug, Clone, Eq, Ord, Hash, PartialOrd, PartialEq, Default)]
@@ -71,11 +72,46 @@ pub fn update_pts(i: &EffectUpdatePtsIn) -> Vec<EffectUpdatePtsOut> {
     trace!("Pre: {}", i.pts);
     let mut pts2 = i.pts.clone();
     for site in &i.effect.does_malloc {
-        pts2.make_stale(site);
+        pts2.make_stale(site, &[]);
     }
     for site in &i.effect.maybe_malloc {
-        pts2.make_dup(site);
+        pts2.make_dup(site, &[]);
     }
     trace!("Post: {}", pts2);
     vec![EffectUpdatePtsOut { pts2 }]
 }
+
+// Clones of objects allocated at site which were handed back to the caller at ret_loc by heap
+// cloning
+fn clones(pts: &PointsTo, site: &Loc, ret_loc: &Loc) -> Vec<Vec<Loc>> {
+    pts.alloc_clones(site)
+        .into_iter()
+        .filter(|clone| {
+            clone.last().map_or(false, |outer| {
+                outer.addr == ret_loc.addr && outer.file_name == ret_loc.file_name
+            })
+        })
+        .collect()
+}
+
+/// Like `update_pts`, but also ages the objects a call returned by heap cloning on a previous
+/// pass through the same return site.
+pub fn update_pts_clones(i: &EffectUpdatePtsClonesIn) -> Vec<EffectUpdatePtsClonesOut> {
+    trace!("Updating pts with effect: {:?}", i.effect);
+    trace!("Pre: {}", i.pts);
+    let mut pts2 = i.pts.clone();
+    for site in &i.effect.does_malloc {
+        pts2.make_stale(site, &[]);
+        for clone in clones(&pts2, site, i.dst) {
+            pts2.make_stale(site, &clone);
+        }
+    }
+    for site in &i.effect.maybe_malloc {
+        pts2.make_dup(site, &[]);
+        for clone in clones(&pts2, site, i.dst) {
+            pts2.make_dup(site, &clone);
+        }
+    }
+    trace!("Post: {}", pts2);
+    vec![EffectUpdatePtsClonesOut { pts2 }]
+}
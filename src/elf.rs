@@ -0,0 +1,425 @@
+//! elf contains a minimal reader for ELF objects, just sufficient to find PLT stubs and the
+//! dynamic symbols they dispatch to.
+//!
+//! We only walk section headers, relocations, and dynamic symbols, and decode the indirect jump in
+//! each stub to find which GOT slot it goes through. The relocation against that slot names the
+//! function the stub stands in for.
+use std::collections::BTreeMap;
+use std::fmt;
+
+const SHT_RELA: u32 = 4;
+const SHT_REL: u32 = 9;
+const SHT_NOBITS: u32 = 8;
+
+const EM_386: u16 = 3;
+const EM_ARM: u16 = 40;
+const EM_X86_64: u16 = 62;
+const EM_AARCH64: u16 = 183;
+
+// Branch target landing pad some AArch64 PLT stubs start with
+const BTI_C: u32 = 0xd503_245f;
+
+// Sections which contain stubs that calls are made to. `.plt.sec` is used in place of `.plt` for
+// the call targets when IBT is enabled, and `.plt.got` holds stubs for functions bound with
+// `-z now` or whose address is also taken.
+const STUB_SECTIONS: &[&str] = &[".plt", ".plt.sec", ".plt.got"];
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseError {
+    /// The file does not start with the ELF magic number
+    NotElf,
+    /// A header or table points outside the file
+    Truncated,
+    /// We only handle little endian objects
+    BigEndian,
+    /// ELF class byte was neither 32 nor 64 bit
+    BadClass(u8),
+    /// We don't know how to decode PLT stubs for this machine
+    UnsupportedMachine(u16),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::NotElf => write!(f, "not an ELF file"),
+            ParseError::Truncated => write!(f, "truncated or malformed ELF file"),
+            ParseError::BigEndian => write!(f, "big endian ELF files are not supported"),
+            ParseError::BadClass(c) => write!(f, "unknown ELF class {}", c),
+            ParseError::UnsupportedMachine(m) => {
+                write!(f, "PLT decoding not supported for ELF machine {}", m)
+            }
+        }
+    }
+}
+
+type Result<T> = ::std::result::Result<T, ParseError>;
+
+/// A PLT stub, and the name of the function it jumps to
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PltPad {
+    pub name: String,
+    pub addr: u64,
+}
+
+struct Section<'a> {
+    name: String,
+    type_: u32,
+    addr: u64,
+    size: u64,
+    link: u32,
+    entsize: u64,
+    data: &'a [u8],
+}
+
+struct Elf<'a> {
+    is_64: bool,
+    machine: u16,
+    sections: Vec<Section<'a>>,
+}
+
+fn read_u8(b: &[u8], off: usize) -> Result<u8> {
+    b.get(off).cloned().ok_or(ParseError::Truncated)
+}
+
+fn read_u16(b: &[u8], off: usize) -> Result<u16> {
+    let s = b.get(off..off + 2).ok_or(ParseError::Truncated)?;
+    Ok(u16::from(s[0]) | (u16::from(s[1]) << 8))
+}
+
+fn read_u32(b: &[u8], off: usize) -> Result<u32> {
+    let s = b.get(off..off + 4).ok_or(ParseError::Truncated)?;
+    let mut out = 0;
+    for (i, byte) in s.iter().enumerate() {
+        out |= u32::from(*byte) << (8 * i);
+    }
+    Ok(out)
+}
+
+fn read_u64(b: &[u8], off: usize) -> Result<u64> {
+    let lo = read_u32(b, off)?;
+    let hi = read_u32(b, off + 4)?;
+    Ok(u64::from(lo) | (u64::from(hi) << 32))
+}
+
+fn slice(b: &[u8], off: u64, len: u64) -> Result<&[u8]> {
+    let start = off as usize;
+    let end = start
+        .checked_add(len as usize)
+        .ok_or(ParseError::Truncated)?;
+    b.get(start..end).ok_or(ParseError::Truncated)
+}
+
+fn c_str(b: &[u8], off: usize) -> Result<String> {
+    let tail = b.get(off..).ok_or(ParseError::Truncated)?;
+    let len = tail.iter().position(|c| *c == 0).unwrap_or(tail.len());
+    Ok(String::from_utf8_lossy(&tail[..len]).into_owned())
+}
+
+impl<'a> Elf<'a> {
+    fn parse(contents: &'a [u8]) -> Result<Self> {
+        if !contents.starts_with(b"\x7fELF") {
+            return Err(ParseError::NotElf);
+        }
+        let is_64 = match read_u8(contents, 4)? {
+            1 => false,
+            2 => true,
+            c => return Err(ParseError::BadClass(c)),
+        };
+        if read_u8(contents, 5)? != 1 {
+            return Err(ParseError::BigEndian);
+        }
+        let machine = read_u16(contents, 18)?;
+        let (shoff, shentsize, shnum, shstrndx) = if is_64 {
+            (
+                read_u64(contents, 0x28)?,
+                read_u16(contents, 0x3A)?,
+                read_u16(contents, 0x3C)?,
+                read_u16(contents, 0x3E)?,
+            )
+        } else {
+            (
+                u64::from(read_u32(contents, 0x20)?),
+                read_u16(contents, 0x2E)?,
+                read_u16(contents, 0x30)?,
+                read_u16(contents, 0x32)?,
+            )
+        };
+
+        // Read the raw headers first, names can only be resolved once we have the string table
+        let mut raw = Vec::new();
+        for i in 0..u64::from(shnum) {
+            let hdr = slice(
+                contents,
+                shoff + i * u64::from(shentsize),
+                u64::from(shentsize),
+            )?;
+            raw.push(if is_64 {
+                (
+                    read_u32(hdr, 0)?,
+                    read_u32(hdr, 4)?,
+                    read_u64(hdr, 0x10)?,
+                    read_u64(hdr, 0x18)?,
+                    read_u64(hdr, 0x20)?,
+                    read_u32(hdr, 0x28)?,
+                    read_u64(hdr, 0x38)?,
+                )
+            } else {
+                (
+                    read_u32(hdr, 0)?,
+                    read_u32(hdr, 4)?,
+                    u64::from(read_u32(hdr, 0xC)?),
+                    u64::from(read_u32(hdr, 0x10)?),
+                    u64::from(read_u32(hdr, 0x14)?),
+                    read_u32(hdr, 0x18)?,
+                    u64::from(read_u32(hdr, 0x24)?),
+                )
+            });
+        }
+
+        let shstrtab = match raw.get(shstrndx as usize) {
+            Some(&(_, _, _, offset, size, _, _)) => slice(contents, offset, size)?,
+            None => &[][..],
+        };
+
+        let mut sections = Vec::new();
+        for (name_off, type_, addr, offset, size, link, entsize) in raw {
+            let data = if type_ == SHT_NOBITS {
+                &[][..]
+            } else {
+                slice(contents, offset, size)?
+            };
+            sections.push(Section {
+                name: c_str(shstrtab, name_off as usize).unwrap_or_default(),
+                type_,
+                addr,
+                size,
+                link,
+                entsize,
+                data,
+            });
+        }
+
+        Ok(Elf {
+            is_64,
+            machine,
+            sections,
+        })
+    }
+
+    fn section(&self, name: &str) -> Option<&Section<'a>> {
+        self.sections.iter().find(|s| s.name == name)
+    }
+
+    fn sym_name(&self, symtab_idx: u32, sym_idx: u64) -> Result<String> {
+        let symtab = self
+            .sections
+            .get(symtab_idx as usize)
+            .ok_or(ParseError::Truncated)?;
+        let strtab = self
+            .sections
+            .get(symtab.link as usize)
+            .ok_or(ParseError::Truncated)?;
+        let sym_size = if self.is_64 { 24 } else { 16 };
+        let name_off = read_u32(symtab.data, (sym_idx * sym_size) as usize)?;
+        c_str(strtab.data, name_off as usize)
+    }
+
+    /// Maps every relocated address which refers to a named symbol to the name of that symbol.
+    fn reloc_names(&self) -> Result<BTreeMap<u64, String>> {
+        let mut out = BTreeMap::new();
+        for sec in &self.sections {
+            let rela = match sec.type_ {
+                SHT_RELA => true,
+                SHT_REL => false,
+                _ => continue,
+            };
+            let entsize = match (self.is_64, rela) {
+                (true, true) => 24,
+                (true, false) => 16,
+                (false, true) => 12,
+                (false, false) => 8,
+            };
+            for i in 0..(sec.size / entsize) {
+                let base = (i * entsize) as usize;
+                let (offset, sym_idx) = if self.is_64 {
+                    (
+                        read_u64(sec.data, base)?,
+                        read_u64(sec.data, base + 8)? >> 32,
+                    )
+                } else {
+                    (
+                        u64::from(read_u32(sec.data, base)?),
+                        u64::from(read_u32(sec.data, base + 4)? >> 8),
+                    )
+                };
+                // Symbol 0 is the null symbol, e.g. IRELATIVE relocations
+                if sym_idx == 0 {
+                    continue;
+                }
+                let name = self.sym_name(sec.link, sym_idx)?;
+                if !name.is_empty() {
+                    out.insert(offset, name);
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    // The GOT base used by i386 PIC stubs, which address their slot relative to %ebx
+    fn got_base(&self) -> Option<u64> {
+        self.section(".got.plt")
+            .or_else(|| self.section(".got"))
+            .map(|s| s.addr)
+    }
+
+    /// Decodes the GOT slot a single x86 stub jumps through, if any.
+    fn x86_slot(&self, stub: &[u8], stub_addr: u64) -> Option<u64> {
+        for pos in 0..stub.len().saturating_sub(5) {
+            if stub[pos] != 0xff {
+                continue;
+            }
+            let disp = read_u32(stub, pos + 2).ok()?;
+            match (stub[pos + 1], self.is_64) {
+                // jmp *disp(%rip)
+                (0x25, true) => {
+                    let next = stub_addr + pos as u64 + 6;
+                    return Some(next.wrapping_add(i64::from(disp as i32) as u64));
+                }
+                // jmp *abs
+                (0x25, false) => return Some(u64::from(disp)),
+                // jmp *disp(%ebx)
+                (0xa3, false) => {
+                    return self
+                        .got_base()
+                        .map(|got| got.wrapping_add(i64::from(disp as i32) as u64) & 0xffff_ffff)
+                }
+                _ => (),
+            }
+        }
+        None
+    }
+
+    /// Finds the address and GOT slot of each x86 stub
+    fn x86_stubs(&self) -> Vec<(u64, u64)> {
+        let mut out = Vec::new();
+        for sec_name in STUB_SECTIONS {
+            let sec = match self.section(sec_name) {
+                Some(sec) => sec,
+                None => continue,
+            };
+            let stub_size = if sec.entsize != 0 && sec.size % sec.entsize == 0 {
+                sec.entsize
+            } else if *sec_name == ".plt.got" && !sec.data.starts_with(&[0xf3, 0x0f, 0x1e]) {
+                // Without IBT, .plt.got stubs are a bare jmp padded to 8 bytes
+                8
+            } else {
+                16
+            };
+            for (i, stub) in sec.data.chunks(stub_size as usize).enumerate() {
+                let stub_addr = sec.addr + i as u64 * stub_size;
+                if let Some(slot) = self.x86_slot(stub, stub_addr) {
+                    out.push((stub_addr, slot));
+                }
+            }
+        }
+        out
+    }
+
+    /// Finds the address and GOT slot of each AArch64 stub, which loads its slot with
+    /// `adrp x16, page; ldr x17, [x16, #offset]`
+    fn aarch64_stubs(&self) -> Vec<(u64, u64)> {
+        let mut out = Vec::new();
+        let sec = match self.section(".plt") {
+            Some(sec) => sec,
+            None => return out,
+        };
+        for pos in (0..sec.data.len()).step_by(4) {
+            let (adrp, ldr) = match (read_u32(sec.data, pos), read_u32(sec.data, pos + 4)) {
+                (Ok(adrp), Ok(ldr)) => (adrp, ldr),
+                _ => break,
+            };
+            if adrp & 0x9f00_001f != 0x9000_0010 || ldr & 0xffc0_03ff != 0xf940_0211 {
+                continue;
+            }
+            let pc = sec.addr + pos as u64;
+            let imm = (u64::from((adrp >> 5) & 0x7_ffff) << 2) | u64::from((adrp >> 29) & 3);
+            // Sign extend the 21 bit page offset
+            let pages = ((imm << 43) as i64 >> 43) as u64;
+            let page = (pc & !0xfff).wrapping_add(pages << 12);
+            let slot = page.wrapping_add(u64::from((ldr >> 10) & 0xfff) * 8);
+            let start = if pos >= 4 && read_u32(sec.data, pos - 4) == Ok(BTI_C) {
+                pc - 4
+            } else {
+                pc
+            };
+            out.push((start, slot));
+        }
+        out
+    }
+
+    /// Finds the address and GOT slot of each ARM stub, which adds the slot's offset from the PC
+    /// to ip in pieces, then jumps through it with `ldr pc, [ip, #offset]!`
+    fn arm_stubs(&self) -> Vec<(u64, u64)> {
+        // Value of an ARM modified immediate operand, an 8 bit value rotated right
+        fn arm_imm(insn: u32) -> u64 {
+            u64::from((insn & 0xff).rotate_right(2 * ((insn >> 8) & 0xf)))
+        }
+        let mut out = Vec::new();
+        let sec = match self.section(".plt") {
+            Some(sec) => sec,
+            None => return out,
+        };
+        for pos in (0..sec.data.len()).step_by(4) {
+            let insns = (
+                read_u32(sec.data, pos),
+                read_u32(sec.data, pos + 4),
+                read_u32(sec.data, pos + 8),
+            );
+            let (add_pc, add_ip, ldr) = match insns {
+                (Ok(add_pc), Ok(add_ip), Ok(ldr)) => (add_pc, add_ip, ldr),
+                _ => break,
+            };
+            if add_pc & 0xffff_f000 != 0xe28f_c000
+                || add_ip & 0xffff_f000 != 0xe28c_c000
+                || ldr & 0xffff_f000 != 0xe5bc_f000
+            {
+                continue;
+            }
+            let pc = sec.addr + pos as u64;
+            // The PC reads as the address of the instruction plus 8
+            let slot = (pc + 8)
+                .wrapping_add(arm_imm(add_pc))
+                .wrapping_add(arm_imm(add_ip))
+                .wrapping_add(u64::from(ldr & 0xfff))
+                & 0xffff_ffff;
+            out.push((pc, slot));
+        }
+        out
+    }
+
+    fn plt_pads(&self) -> Result<Vec<PltPad>> {
+        let stubs = match self.machine {
+            EM_386 | EM_X86_64 => self.x86_stubs(),
+            EM_AARCH64 => self.aarch64_stubs(),
+            EM_ARM => self.arm_stubs(),
+            m => return Err(ParseError::UnsupportedMachine(m)),
+        };
+        let names = self.reloc_names()?;
+        // Stubs which don't go through a named slot (e.g. PLT0, or lazy binding stubs when
+        // .plt.sec is present) just won't match anything here.
+        Ok(stubs
+            .into_iter()
+            .filter_map(|(addr, slot)| {
+                names.get(&slot).map(|name| PltPad {
+                    name: name.clone(),
+                    addr,
+                })
+            })
+            .collect())
+    }
+}
+
+/// Finds all PLT stubs in an ELF object, along with the name of the function they call.
+pub fn plt_pads(contents: &[u8]) -> Result<Vec<PltPad>> {
+    Elf::parse(contents)?.plt_pads()
+}
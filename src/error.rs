@@ -0,0 +1,49 @@
+use serde_json;
+use std::fmt::{Display, Formatter};
+use std::io;
+
+/// Errors which prevent an analysis database from being constructed.
+///
+/// Problems with the contents of an input (e.g. an unparseable binary, or an address which won't
+/// lift) do not cause an `Error`, they are recorded in the `load_diagnostic` relation instead.
+#[derive(Debug)]
+pub enum Error {
+    /// An input file could not be read
+    Io {
+        /// Path of the input
+        file_name: String,
+        /// Underlying error from the OS
+        source: io::Error,
+    },
+    /// An allocator spec could not be parsed
+    AllocSpec {
+        /// Path of the spec
+        file_name: String,
+        /// What was wrong with it
+        source: serde_json::Error,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> ::std::fmt::Result {
+        match *self {
+            Error::Io {
+                ref file_name,
+                ref source,
+            } => write!(f, "could not read {}: {}", file_name, source),
+            Error::AllocSpec {
+                ref file_name,
+                ref source,
+            } => write!(f, "invalid allocator spec {}: {}", file_name, source),
+        }
+    }
+}
+
+impl ::std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn ::std::error::Error + 'static)> {
+        match *self {
+            Error::Io { ref source, .. } => Some(source),
+            Error::AllocSpec { ref source, .. } => Some(source),
+        }
+    }
+}
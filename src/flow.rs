@@ -1,8 +1,10 @@
+use crate::constraints::generation::{self, Globals, E};
 use crate::constraints::{Constraint, VarPath};
 use crate::datalog::*;
+use crate::load::{self, Loc, Stack};
 use crate::points_to::{PointsTo, VarRef, VarSet};
-use crate::regs::ARGS;
 use crate::var::Var;
+use std::collections::{BTreeMap, BTreeSet};
 
 fn off_plus(base: &mut Option<u64>, off: Option<u64>) {
     match off {
@@ -82,7 +84,7 @@ fn apply(pts: &mut PointsTo, c: &Constraint) {
     trace!("Applying {}", c);
     for rhs in &c.rhss {
         if let Var::Alloc { ref site, .. } = rhs.base {
-            pts.make_stale(site);
+            pts.make_stale(site, &[]);
         }
     }
 
@@ -135,16 +137,191 @@ pub fn is_freed(i: &FlowIsFreedIn) -> Vec<FlowIsFreedOut> {
         .collect()
 }
 
+pub fn dead_frames(i: &FlowDeadFramesIn) -> Vec<FlowDeadFramesOut> {
+    i.pts
+        .dead_frames(i.v)
+        .into_iter()
+        .map(|ret| FlowDeadFramesOut { ret })
+        .collect()
+}
+
+pub fn double_free(i: &FlowDoubleFreeIn) -> Vec<FlowDoubleFreeOut> {
+    i.args
+        .iter()
+        .flat_map(|arg_n| {
+            let v = Var::arg(*arg_n, i.base, i.conv);
+            i.pts
+                .free_sites(&v)
+                .into_iter()
+                .map(move |free| FlowDoubleFreeOut { v: v.clone(), free })
+        })
+        .collect()
+}
+
+pub fn invalid_free(i: &FlowInvalidFreeIn) -> Vec<FlowInvalidFreeOut> {
+    let mut out = Vec::new();
+    for arg_n in i.args.iter() {
+        let v = Var::arg(*arg_n, i.base, i.conv);
+        for obj in i
+            .pts
+            .get(&VarRef {
+                var: v.clone(),
+                offset: Some(0),
+            })
+            .iter()
+        {
+            let invalid = match obj.var {
+                Var::StackSlot { .. } | Var::Global { .. } => true,
+                // An unknown offset may well be 0, so only flag interior pointers we're sure of
+                Var::Alloc { .. } => obj.offset.map_or(false, |offset| offset != 0),
+                _ => false,
+            };
+            if invalid {
+                out.push(FlowInvalidFreeOut {
+                    v: v.clone(),
+                    obj: obj.clone(),
+                });
+            }
+        }
+    }
+    out
+}
+
+pub fn leaks(i: &FlowLeaksIn) -> Vec<FlowLeaksOut> {
+    let after = i.pts2.alloc_sites();
+    // Objects passed to free at this instruction will be dropped without being marked freed if
+    // nothing else refers to them, so don't report those.
+    let mut freeing = BTreeSet::new();
+    for c in i.cs {
+        if c.rhss.iter().any(|rhs| rhs.base.is_freed()) {
+            for obj in i
+                .pts
+                .get(&VarRef {
+                    var: c.lhs.base.clone(),
+                    offset: Some(0),
+                })
+                .iter()
+            {
+                if let Var::Alloc { ref site, .. } = obj.var {
+                    freeing.insert(site.clone());
+                }
+            }
+        }
+    }
+    i.pts
+        .alloc_sites()
+        .into_iter()
+        .filter(|site| !after.contains(site) && !freeing.contains(site) && !i.pts.alloc_freed(site))
+        .map(|alloc| FlowLeaksOut { alloc })
+        .collect()
+}
+
+pub fn witnesses(i: &FlowWitnessesIn) -> Vec<FlowWitnessesOut> {
+    i.pts
+        .freed_objects(i.v)
+        .into_iter()
+        .filter_map(|(obj, free)| match obj {
+            Var::Alloc { site, .. } => Some(FlowWitnessesOut { alloc: site, free }),
+            _ => None,
+        })
+        .collect()
+}
+
+// Reads a function pointer of word_size bytes stored at offset into a read-only segment
+fn read_code_ptr(
+    segments: &[(Loc, Vec<u8>)],
+    code: &[(Loc, u64)],
+    seg: &Loc,
+    offset: u64,
+    word_size: usize,
+) -> Option<Loc> {
+    let contents = &segments.iter().find(|&&(ref start, _)| start == seg)?.1;
+    let offset = offset as usize;
+    let word = contents
+        .get(offset..offset.checked_add(word_size)?)?
+        .iter()
+        .rev()
+        .fold(0, |acc, b| (acc << 8) | u64::from(*b));
+    code.iter()
+        .find(|&&(ref start, end)| {
+            start.file_name == seg.file_name && start.addr <= word && word <= end
+        })
+        .map(|_| Loc {
+            file_name: seg.file_name,
+            addr: word,
+            stack: Stack::NoStack,
+        })
+}
+
+pub fn call_targets(i: &FlowCallTargetsIn) -> Vec<FlowCallTargetsOut> {
+    let globals = Globals {
+        rip: Some(i.fallthrough.addr),
+        ranges: i.ranges,
+        rodata: i.rodata,
+        code: i.code,
+    };
+    // Calls don't generate constraints, but the target may be computed into a temporary by the
+    // call instruction itself.
+    let mut pts = i.pts.clone();
+    for c in generation::extract_constraints(i.bil, i.loc, i.base, globals) {
+        apply(&mut pts, &c);
+    }
+    let mut targets = BTreeSet::new();
+    for jump in load::computed_jumps(i.bil) {
+        for e in generation::extract_expr(jump, i.loc, i.base, &BTreeMap::new(), globals) {
+            let vp = match e {
+                E::VP(vp) => vp,
+                E::Const(_) => continue,
+            };
+            // Tables of function pointers in read-only data, like vtables, aren't part of the
+            // points-to state, so read the slot being called through from the segment
+            if vp.derefs() > 1 {
+                let mut slot = vp.clone();
+                slot.offsets.pop();
+                for vr in rhs_resolve(&pts, slot) {
+                    if let (&Var::Global { ref addr }, Some(offset)) = (&vr.var, vr.offset) {
+                        targets.extend(read_code_ptr(
+                            i.segments,
+                            i.code,
+                            addr,
+                            offset,
+                            i.conv.word_size,
+                        ));
+                    }
+                }
+            }
+            for vr in rhs_resolve(&pts, vp) {
+                if let (Var::Code { addr }, Some(0)) = (vr.var, vr.offset) {
+                    targets.insert(addr);
+                }
+            }
+        }
+    }
+    let stack = load::callee_stack(i.loc, i.fallthrough, *i.depth);
+    targets
+        .into_iter()
+        .map(|target| FlowCallTargetsOut {
+            dst: Loc {
+                stack: stack.clone(),
+                ..target.clone()
+            },
+            target,
+        })
+        .collect()
+}
+
 pub fn stack_purge(i: &FlowStackPurgeIn) -> Vec<FlowStackPurgeOut> {
     let mut pts = i.pts.clone();
     trace!("stack_purge@{}->{}", i.src, i.dst);
     trace!("pre: {}", pts);
     pts.clear_live();
+    let stack_args = pts.pass_stack_args(&i.dst, i.conv.word_size, i.offsets);
+    pts.push_callers();
     pts.clear_frames();
-    pts.only_regs(ARGS);
+    pts.only_regs(i.conv.args);
     //TODO: Now that I have clear_frames, can drop_stack here be replaced by a call to
     //canonicalize()?
-    pts.drop_stack();
+    pts.drop_stack(&stack_args);
     let new_live: Vec<_> = i
         .pts
         .pt_to()
@@ -161,10 +338,57 @@ pub fn dyn_clear(i: &FlowDynClearIn) -> Vec<FlowDynClearOut> {
     let mut pts = i.pts.clone();
     pts.clear_live();
     pts.clear_frames();
+    pts.pop_caller(i.base);
     pts.add_frame(i.base.clone());
     vec![FlowDynClearOut { pts2: pts }]
 }
 
+/// Like `dyn_clear`, but if the callee returns a fresh heap object it allocated itself, the object
+/// is renamed after the return site, so allocation wrappers produce one object per caller.
+pub fn dyn_clone(i: &FlowDynCloneIn) -> Vec<FlowDynCloneOut> {
+    let mut pts = i.pts.clone();
+    let ret = VarRef {
+        var: Var::Register {
+            register: i.conv.ret,
+        },
+        offset: Some(0),
+    };
+    let mut clones = Vec::new();
+    for obj in pts.get(&ret).iter() {
+        if let Var::Alloc {
+            ref site,
+            ref clone,
+            stale: false,
+        } = obj.var
+        {
+            // Sites are only cloned by the function which allocated them, or which received them
+            // from the last wrapper they were cloned by.
+            let outer = clone.last().unwrap_or(site);
+            if clone.len() < *i.depth
+                && outer.file_name == i.callee.file_name
+                && outer.addr >= i.callee.addr
+                && outer.addr <= *i.end
+            {
+                let mut renamed = clone.clone();
+                renamed.push(Loc {
+                    stack: Stack::NoStack,
+                    ..i.dst.clone()
+                });
+                clones.push((site.clone(), clone.clone(), renamed));
+            }
+        }
+    }
+    for (site, clone, renamed) in clones {
+        pts.make_stale(&site, &renamed);
+        pts.rename_alloc(&site, &clone, &renamed);
+    }
+    pts.clear_live();
+    pts.clear_frames();
+    pts.pop_caller(i.base);
+    pts.add_frame(i.base.clone());
+    vec![FlowDynCloneOut { pts2: pts }]
+}
+
 pub fn base_pts(i: &FlowBasePtsIn) -> Vec<FlowBasePtsOut> {
     vec![FlowBasePtsOut {
         pts: PointsTo::new(i.loc.clone()),
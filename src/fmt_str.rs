@@ -1,19 +1,23 @@
 use crate::datalog::*;
-use crate::regs::ARGS;
+use crate::regs::Reg;
+use crate::var::Var;
 use bap::basic::Cast;
-use bap::high::bil::{Expression, Statement};
+use bap::high::bil::{BinOp, Expression, Statement};
 use bap::high::bitvector::BitVector;
 use num_traits::ToPrimitive;
+use std::str::FromStr;
 
-fn const_collapse(e: &Expression) -> Option<BitVector> {
+// Folds an expression to a constant, taking RIP to be the address of the next instruction
+pub fn const_collapse(e: &Expression, rip: u64) -> Option<BitVector> {
     match *e {
         Expression::Const(ref bv) => Some(bv.clone()),
+        Expression::Var(ref v) if v.name == "RIP" => Some(BitVector::from_u64(rip, 64)),
         Expression::Cast {
             width,
             ref kind,
             ref arg,
         } => {
-            if let Some(i) = const_collapse(arg) {
+            if let Some(i) = const_collapse(arg, rip) {
                 match *kind {
                     Cast::Low | Cast::Unsigned => {
                         Some(BitVector::from_u64(i.to_u64().unwrap(), width as usize))
@@ -24,44 +28,200 @@ fn const_collapse(e: &Expression) -> Option<BitVector> {
                 None
             }
         }
+        Expression::BinOp {
+            op: BinOp::Add,
+            ref lhs,
+            ref rhs,
+        } => {
+            let l = const_collapse(lhs, rip)?.to_u64()?;
+            let r = const_collapse(rhs, rip)?.to_u64()?;
+            Some(BitVector::from_u64(l.wrapping_add(r), 64))
+        }
+        _ => None,
+    }
+}
+
+// Register copied by a move, looking through width casts
+fn reg_collapse(e: &Expression) -> Option<Reg> {
+    match *e {
+        Expression::Var(ref v) => Reg::from_str(v.name.as_str()).ok(),
+        Expression::Cast {
+            ref kind, ref arg, ..
+        } => match *kind {
+            Cast::Low | Cast::Unsigned => reg_collapse(arg),
+            _ => None,
+        },
         _ => None,
     }
 }
 
+// Index of the format string argument of printf-family functions. Functions taking a va_list
+// are left out, since their arguments aren't visible at the call site.
+fn fmt_arg_index(func_name: &str) -> Option<usize> {
+    match func_name {
+        "printf" | "warn" | "warnx" => Some(0),
+        "fprintf" | "sprintf" | "dprintf" | "asprintf" | "syslog" | "err" | "errx"
+        | "__printf_chk" => Some(1),
+        "snprintf" | "__fprintf_chk" | "__dprintf_chk" | "__asprintf_chk" | "__syslog_chk" => {
+            Some(2)
+        }
+        "__sprintf_chk" => Some(3),
+        "__snprintf_chk" => Some(4),
+        _ => None,
+    }
+}
+
+pub fn printf_fmt_arg(i: &FmtStrPrintfFmtArgIn) -> Vec<FmtStrPrintfFmtArgOut> {
+    fmt_arg_index(i.func_name)
+        .map(|fmt_arg| FmtStrPrintfFmtArgOut { fmt_arg })
+        .into_iter()
+        .collect()
+}
+
+pub fn fmt_reg(i: &FmtStrFmtRegIn) -> Vec<FmtStrFmtRegOut> {
+    i.conv
+        .arg(*i.fmt_arg)
+        .map(|register| FmtStrFmtRegOut { register })
+        .into_iter()
+        .collect()
+}
+
+// Right hand side of the last move to a register in an instruction
+fn reg_move<'a>(bil: &'a [Statement], register: Reg) -> Option<&'a Expression> {
+    bil.iter()
+        .filter_map(|stmt| match *stmt {
+            Statement::Move { ref lhs, ref rhs } => {
+                if Reg::from_str(lhs.name.as_str()) == Ok(register) {
+                    Some(rhs)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        })
+        .last()
+}
+
 pub fn const_move(i: &FmtStrConstMoveIn) -> Vec<FmtStrConstMoveOut> {
-    for stmt in i.bil {
-        if let Statement::Move { ref lhs, ref rhs } = *stmt {
-            if lhs.name != "RDI" {
-                continue;
+    reg_move(i.bil, *i.register)
+        .and_then(|rhs| const_collapse(rhs, i.fallthrough.addr))
+        .map(|bv| FmtStrConstMoveOut {
+            addr: bv.to_u64().unwrap(),
+        })
+        .into_iter()
+        .collect()
+}
+
+pub fn reg_copy(i: &FmtStrRegCopyIn) -> Vec<FmtStrRegCopyOut> {
+    reg_move(i.bil, *i.register)
+        .and_then(reg_collapse)
+        .map(|src| FmtStrRegCopyOut { src })
+        .into_iter()
+        .collect()
+}
+
+// What a single conversion specification reads from the variadic arguments
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum ArgClass {
+    // Passed in a general purpose register or stack slot, and not dereferenced
+    Int,
+    // Passed in a vector register, so it doesn't shift the general purpose arguments
+    Float,
+    // A pointer which printf reads through (%s) or writes through (%n)
+    Pointer,
+}
+
+fn conversion_class(c: char) -> Option<ArgClass> {
+    match c {
+        'd' | 'i' | 'o' | 'u' | 'x' | 'X' | 'c' | 'C' => Some(ArgClass::Int),
+        'e' | 'E' | 'f' | 'F' | 'g' | 'G' | 'a' | 'A' => Some(ArgClass::Float),
+        's' | 'S' | 'p' | 'n' => Some(ArgClass::Pointer),
+        _ => None,
+    }
+}
+
+// Parses a C format string into the sequence of argument classes it consumes.
+//
+// Parsing stops at the first conversion we don't understand, or at a positional (`%1$s`)
+// argument, since we can no longer tell which arguments later conversions refer to.
+fn parse_args(fmt: &str) -> Vec<ArgClass> {
+    let mut out = Vec::new();
+    let mut cs = fmt.chars().peekable();
+    while let Some(c) = cs.next() {
+        if c != '%' {
+            continue;
+        }
+        if cs.peek() == Some(&'%') {
+            cs.next();
+            continue;
+        }
+        // Flags
+        while let Some(&c) = cs.peek() {
+            if "-+ #0'I".contains(c) {
+                cs.next();
+            } else {
+                break;
+            }
+        }
+        // Field width and precision, each of which may be taken from an int argument
+        let mut width = true;
+        loop {
+            match cs.peek() {
+                Some(&'*') => {
+                    cs.next();
+                    out.push(ArgClass::Int);
+                }
+                Some(&d) if d.is_digit(10) => {
+                    while cs.peek().map_or(false, |d| d.is_digit(10)) {
+                        cs.next();
+                    }
+                    if cs.peek() == Some(&'$') {
+                        return out;
+                    }
+                }
+                _ => (),
             }
-            if let Some(bv) = const_collapse(rhs) {
-                return vec![FmtStrConstMoveOut {
-                    addr: bv.to_u64().unwrap(),
-                }];
+            if width && cs.peek() == Some(&'.') {
+                cs.next();
+                width = false;
+            } else {
+                break;
+            }
+        }
+        // Length modifiers don't change which register class the argument is passed in
+        while let Some(&c) = cs.peek() {
+            if "hlLqjzZt".contains(c) {
+                cs.next();
+            } else {
+                break;
             }
         }
+        match cs.next() {
+            // %m prints strerror(errno), and takes no argument
+            Some('m') => (),
+            Some(c) => match conversion_class(c) {
+                Some(class) => out.push(class),
+                None => return out,
+            },
+            None => return out,
+        }
     }
-    Vec::new()
+    out
 }
 
 pub fn parse_str(i: &FmtStrParseStrIn) -> Vec<FmtStrParseStrOut> {
-    let mut skip = false;
-    let mut arg = 1;
+    // Variadic arguments start right after the format string
+    let mut arg = *i.fmt_arg + 1;
     let mut out = Vec::new();
-    let cs: Vec<_> = i.str.chars().collect();
-    for c in (&cs).windows(2) {
-        if skip {
-            skip = false;
-            continue;
-        }
-        if c[0] == '%' {
-            if c[1] == '%' {
-                skip = true;
-            } else if arg < ARGS.len() {
-                out.push(FmtStrParseStrOut { arg: ARGS[arg] });
-                arg += 1;
-            }
+    for class in parse_args(i.str) {
+        match class {
+            ArgClass::Float => continue,
+            ArgClass::Int => (),
+            ArgClass::Pointer => out.push(FmtStrParseStrOut {
+                var: Var::arg(arg, i.base, i.conv),
+            }),
         }
+        arg += 1;
     }
     out
 }
@@ -0,0 +1,259 @@
+//! jump_table recovers the targets of indirect jumps through tables, as emitted for `switch`
+//! statements.
+//!
+//! Two layouts are recognized:
+//!
+//! * Absolute tables of 8 byte code addresses indexed by a register, either jumped through
+//!   directly (`jmp [table + r*8]`) or loaded into a register first.
+//! * Position independent tables of 4 byte offsets relative to the table itself, whose address is
+//!   loaded RIP-relative (`lea rdx, [rip + table]`) and added to the entry (`add rax, rdx`)
+//!   before the jump.
+//!
+//! The table's length comes from the bounds check guarding the jump, `cmp idx, N; ja default`,
+//! which reaches the jump along straight line code. Exactly `N + 1` entries are read, so data
+//! placed right after the table isn't taken for more entries.
+use crate::datalog::*;
+use crate::fmt_str::const_collapse;
+use crate::load::{self, Loc};
+use crate::regs::Reg;
+use bap::basic::Cast;
+use bap::high::bil::{BinOp, Expression, Statement, Variable};
+use num_traits::ToPrimitive;
+use std::str::FromStr;
+
+// Largest bounds check accepted as guarding a table
+const MAX_ENTRIES: usize = 1024;
+
+fn strip_casts(e: &Expression) -> &Expression {
+    match *e {
+        Expression::Cast {
+            ref kind, ref arg, ..
+        } => match *kind {
+            Cast::Low | Cast::Unsigned | Cast::Signed => strip_casts(arg),
+            _ => e,
+        },
+        _ => e,
+    }
+}
+
+// Base address of a load from a constant plus a computed index, e.g. `[table + r*8]`
+fn indexed_load(e: &Expression) -> Option<u64> {
+    let index = match *strip_casts(e) {
+        Expression::Load { ref index, .. } => index,
+        _ => return None,
+    };
+    match *strip_casts(index) {
+        Expression::BinOp {
+            op: BinOp::Add,
+            ref lhs,
+            ref rhs,
+        } => match (const_collapse(lhs, 0), const_collapse(rhs, 0)) {
+            (Some(k), None) | (None, Some(k)) => k.to_u64(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Right hand side of the last move to a register in an instruction
+fn reg_move(bil: &[Statement], register: Reg) -> Option<&Expression> {
+    bil.iter()
+        .filter_map(|stmt| match *stmt {
+            Statement::Move { ref lhs, ref rhs } => {
+                if Reg::from_str(lhs.name.as_str()) == Ok(register) {
+                    Some(rhs)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        })
+        .last()
+}
+
+fn is_flag(v: &Variable, flag: &str) -> bool {
+    v.name == flag
+}
+
+fn mentions_flag(e: &Expression, flag: &str) -> bool {
+    match *e {
+        Expression::Var(ref v) => is_flag(v, flag),
+        Expression::UnOp { ref arg, .. } | Expression::Cast { ref arg, .. } => {
+            mentions_flag(arg, flag)
+        }
+        Expression::BinOp {
+            ref lhs, ref rhs, ..
+        } => mentions_flag(lhs, flag) || mentions_flag(rhs, flag),
+        _ => false,
+    }
+}
+
+fn has_jump(bil: &[Statement]) -> bool {
+    bil.iter().any(|stmt| match *stmt {
+        Statement::Jump(_) => true,
+        Statement::IfThenElse {
+            ref then_clause,
+            ref else_clause,
+            ..
+        } => has_jump(then_clause) || has_jump(else_clause),
+        Statement::While { ref body, .. } => has_jump(body),
+        _ => false,
+    })
+}
+
+// Whether an instruction is a `ja`, i.e. jumps when neither CF nor ZF is set
+fn is_jump_above(bil: &[Statement]) -> bool {
+    bil.iter().any(|stmt| match *stmt {
+        Statement::IfThenElse {
+            ref cond,
+            ref then_clause,
+            ref else_clause,
+        } => {
+            mentions_flag(cond, "CF")
+                && mentions_flag(cond, "ZF")
+                && has_jump(then_clause)
+                && !has_jump(else_clause)
+        }
+        _ => false,
+    })
+}
+
+// `N` in `cmp x, N`, which sets `CF := x < N`
+fn cmp_const(bil: &[Statement]) -> Option<u64> {
+    bil.iter()
+        .filter_map(|stmt| match *stmt {
+            Statement::Move { ref lhs, ref rhs } if is_flag(lhs, "CF") => Some(rhs),
+            _ => None,
+        })
+        .last()
+        .and_then(|rhs| match *strip_casts(rhs) {
+            Expression::BinOp {
+                op: BinOp::LT,
+                ref rhs,
+                ..
+            } => const_collapse(rhs, 0).and_then(|bv| bv.to_u64()),
+            _ => None,
+        })
+}
+
+fn read_le(contents: &[u8], offset: usize, width: usize) -> Option<u64> {
+    Some(
+        contents
+            .get(offset..offset + width)?
+            .iter()
+            .rev()
+            .fold(0, |acc, b| (acc << 8) | u64::from(*b)),
+    )
+}
+
+// Datalog functions
+
+pub fn jump_reg(i: &JumpTableJumpRegIn) -> Vec<JumpTableJumpRegOut> {
+    load::computed_jumps(i.bil)
+        .into_iter()
+        .filter_map(|e| match *strip_casts(e) {
+            Expression::Var(ref v) => Reg::from_str(v.name.as_str()).ok(),
+            _ => None,
+        })
+        .map(|register| JumpTableJumpRegOut { register })
+        .collect()
+}
+
+pub fn sum_operands(i: &JumpTableSumOperandsIn) -> Vec<JumpTableSumOperandsOut> {
+    let (lhs, rhs) = match reg_move(i.bil, *i.register).map(strip_casts) {
+        Some(&Expression::BinOp {
+            op: BinOp::Add,
+            ref lhs,
+            ref rhs,
+        }) => (lhs, rhs),
+        _ => return Vec::new(),
+    };
+    [lhs, rhs]
+        .iter()
+        .filter_map(|e| match *strip_casts(e) {
+            Expression::Var(ref v) => Reg::from_str(v.name.as_str()).ok(),
+            _ => None,
+        })
+        .map(|operand| JumpTableSumOperandsOut { operand })
+        .collect()
+}
+
+pub fn direct(i: &JumpTableDirectIn) -> Vec<JumpTableDirectOut> {
+    load::computed_jumps(i.bil)
+        .into_iter()
+        .filter_map(indexed_load)
+        .map(|addr| JumpTableDirectOut { addr })
+        .collect()
+}
+
+pub fn loaded(i: &JumpTableLoadedIn) -> Vec<JumpTableLoadedOut> {
+    reg_move(i.bil, *i.register)
+        .and_then(indexed_load)
+        .map(|addr| JumpTableLoadedOut { addr })
+        .into_iter()
+        .collect()
+}
+
+pub fn base(i: &JumpTableBaseIn) -> Vec<JumpTableBaseOut> {
+    reg_move(i.bil, *i.register)
+        .and_then(|rhs| const_collapse(rhs, i.fallthrough.addr))
+        .and_then(|bv| bv.to_u64())
+        .map(|addr| JumpTableBaseOut { addr })
+        .into_iter()
+        .collect()
+}
+
+pub fn cmp_bound(i: &JumpTableCmpBoundIn) -> Vec<JumpTableCmpBoundOut> {
+    if !is_jump_above(i.ja_bil) {
+        return Vec::new();
+    }
+    cmp_const(i.bil)
+        .filter(|bound| *bound < MAX_ENTRIES as u64)
+        .map(|bound| JumpTableCmpBoundOut { bound })
+        .into_iter()
+        .collect()
+}
+
+pub fn straight(i: &JumpTableStraightIn) -> Vec<JumpTableStraightOut> {
+    if has_jump(i.bil) {
+        vec![]
+    } else {
+        vec![JumpTableStraightOut {}]
+    }
+}
+
+pub fn entries(i: &JumpTableEntriesIn) -> Vec<JumpTableEntriesOut> {
+    let (start, contents) = match i.segments.iter().find(|&&(ref start, ref contents)| {
+        start.file_name == i.jump.file_name
+            && start.addr <= *i.addr
+            && *i.addr < start.addr + contents.len() as u64
+    }) {
+        Some(&(ref start, ref contents)) => (start.addr, contents),
+        None => return Vec::new(),
+    };
+    let width = if *i.relative { 4 } else { 8 };
+    let table = (*i.addr - start) as usize;
+    let mut out = Vec::new();
+    for n in 0..=*i.bound as usize {
+        let entry = match read_le(contents, table + n * width, width) {
+            Some(entry) => entry,
+            None => return Vec::new(),
+        };
+        let target = if *i.relative {
+            i.addr.wrapping_add(entry as u32 as i32 as i64 as u64)
+        } else {
+            entry
+        };
+        // A target outside the function means this isn't the table the guard checks against
+        if target < i.base.addr || target > *i.end {
+            return Vec::new();
+        }
+        out.push(JumpTableEntriesOut {
+            dst: Loc {
+                addr: target,
+                ..i.jump.clone()
+            },
+        });
+    }
+    out
+}
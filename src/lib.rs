@@ -16,7 +16,6 @@ extern crate bap;
 extern crate lazy_static;
 #[macro_use]
 extern crate log;
-extern crate mktemp;
 #[macro_use]
 extern crate mycroft_macros;
 extern crate mycroft_support;
@@ -24,23 +23,34 @@ extern crate num_traits;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde;
+extern crate serde_json;
 
+mod alloc_spec;
 mod constraints;
 mod context;
 mod datalog;
 mod effect;
+mod elf;
+mod error;
 mod flow;
 mod fmt_str;
 mod interned_string;
+mod jump_table;
 mod live;
 mod load;
+mod pe;
 mod points_to;
 mod printers;
 mod regs;
+mod summaries;
 mod uaf;
 mod use_def;
 mod var;
+mod witness;
+pub use crate::alloc_spec::{AllocResult, AllocSpec, Allocator, Deallocator};
 pub use crate::datalog::*;
+pub use crate::error::Error;
+pub use crate::witness::{Edge, Step, Witnesses};
 
 #[derive(Eq, Copy, Debug, PartialEq, Clone, Ord, PartialOrd, Serialize, Deserialize)]
 /// Describes the kind of location to use in the dataflow analysis.
@@ -48,7 +58,7 @@ pub enum LocType {
     /// Parameterize location solely based on instruction pointer
     Addr,
     /// Augment the instruction pointer with a fixed length callstack
-    /// (compile time constant configurable, currently 1)
+    /// (length set by `Config::stack_depth`)
     AddrAndStack,
 }
 
@@ -64,14 +74,36 @@ pub struct Config {
     /// as though they were non-aliasing structs with width 4 and depth 2.
     /// These values are also compile time constants.
     pub undef_hack: bool,
+    /// If true, reports heap objects which become unreachable without being freed.
+    #[serde(default)]
+    pub leaks: bool,
+    /// Additional allocator and deallocator models to use alongside the builtin ones. The spec is
+    /// borrowed for the life of the program (e.g. a leaked `AllocSpec::from_file` result) so that
+    /// `Config` stays `Copy`, and isn't serialized.
+    #[serde(skip)]
+    pub alloc_spec: Option<&'static AllocSpec>,
+    /// How many return sites to keep in the callstack of a location when context sensitive
+    #[serde(default = "Config::default_stack_depth")]
+    pub stack_depth: usize,
+    /// If true, heap objects returned by allocation wrappers are named after the return site
+    /// as well as the allocation site, so objects allocated on behalf of different callers are
+    /// kept apart. Names carry at most `stack_depth` return sites.
+    #[serde(default)]
+    pub heap_clone: bool,
 }
 
 impl Config {
+    const DEFAULT_STACK_DEPTH: usize = 1;
+
     /// Default config for context sensitive analysis
     pub const CONTEXT_SENSITIVE: Self = Self {
         loc_type: LocType::AddrAndStack,
         load_only: false,
         undef_hack: false,
+        leaks: false,
+        alloc_spec: None,
+        stack_depth: Config::DEFAULT_STACK_DEPTH,
+        heap_clone: false,
     };
 
     /// Default config for context insensitive analysis
@@ -79,6 +111,10 @@ impl Config {
         loc_type: LocType::Addr,
         load_only: false,
         undef_hack: false,
+        leaks: false,
+        alloc_spec: None,
+        stack_depth: Config::DEFAULT_STACK_DEPTH,
+        heap_clone: false,
     };
 
     /// Default config to just load
@@ -86,8 +122,16 @@ impl Config {
         loc_type: LocType::Addr,
         load_only: true,
         undef_hack: false,
+        leaks: false,
+        alloc_spec: None,
+        stack_depth: Config::DEFAULT_STACK_DEPTH,
+        heap_clone: false,
     };
 
+    fn default_stack_depth() -> usize {
+        Self::DEFAULT_STACK_DEPTH
+    }
+
     /// Whether the configuration implies flow sensitivity
     pub fn uses_flow(self) -> bool {
         !self.load_only
@@ -106,19 +150,32 @@ impl Config {
     pub fn defines_undef(self) -> bool {
         self.undef_hack
     }
+
+    /// Whether to search for memory leaks
+    pub fn detects_leaks(self) -> bool {
+        self.leaks && self.uses_flow()
+    }
 }
 
 /// Produces a mycroft database containing your input files and the
 /// configured rules. You must run the rules in the database
 /// before queries will return any useful output.
-pub fn uaf(files: &[String], config: Config) -> Database {
+///
+/// Fails only if an input cannot be read. Inputs which can be read but not loaded or lifted
+/// produce facts in `load_diagnostic` once the rules are run, which can be retrieved with
+/// `query_load_diagnostic`.
+pub fn uaf(files: &[String], config: Config) -> Result<Database, Error> {
     let mut db = Database::new();
     for file_name in files {
         use std::fs::File;
         use std::io::Read;
         let mut in_raw = Vec::new();
-        let mut in_file = File::open(file_name).unwrap();
-        in_file.read_to_end(&mut in_raw).unwrap();
+        File::open(file_name)
+            .and_then(|mut in_file| in_file.read_to_end(&mut in_raw))
+            .map_err(|source| Error::Io {
+                file_name: file_name.to_string(),
+                source,
+            })?;
 
         db.insert_file(datalog::File {
             name: file_name.to_string(),
@@ -132,9 +189,32 @@ pub fn uaf(files: &[String], config: Config) -> Database {
     if config.uses_ctx() {
         db.insert_context_enable(datalog::ContextEnable { arg0: true });
     }
+    db.insert_context_depth(datalog::ContextDepth {
+        arg0: config.stack_depth,
+    });
+    db.insert_heap_clone(datalog::HeapClone {
+        arg0: config.heap_clone,
+    });
     if config.defines_undef() {
         db.insert_undef_hack(datalog::UndefHack { arg0: true });
     }
+    if config.detects_leaks() {
+        db.insert_leak_enable(datalog::LeakEnable { arg0: true });
+    }
+    if let Some(spec) = config.alloc_spec {
+        for allocator in &spec.allocators {
+            db.insert_alloc_model(datalog::AllocModel {
+                pattern: allocator.name.clone(),
+                result: allocator.result,
+            });
+        }
+        for deallocator in &spec.deallocators {
+            db.insert_dealloc_model(datalog::DeallocModel {
+                pattern: deallocator.name.clone(),
+                args: deallocator.args.clone(),
+            });
+        }
+    }
 
-    db
+    Ok(db)
 }
@@ -1,7 +1,7 @@
 use crate::datalog::*;
 use crate::load::Loc;
 use crate::points_to::{PointsTo, VarRef, VarSet};
-use crate::regs::Reg;
+use crate::regs::{self, Reg};
 use crate::var::{var_args, Var};
 use bap::high::bil;
 use std::collections::BTreeMap;
@@ -17,6 +17,8 @@ pub fn not_defined(i: &LiveNotDefinedIn) -> Vec<LiveNotDefinedOut> {
     }
 }
 
+// Globals are never purged from points-to sets, so liveness doesn't model them, and constant
+// addresses are left as constants.
 fn defined_walk(
     lhs: &bil::Variable,
     rhs: &bil::Expression,
@@ -24,7 +26,7 @@ fn defined_walk(
     func_addr: &Loc,
     tmp_db: &mut BTreeMap<Var, u64>,
 ) -> Vec<Var> {
-    use crate::constraints::generation::{extract_expr, E};
+    use crate::constraints::generation::{extract_expr, Globals, E};
     let mut out = Vec::new();
     //TODO dedup
     match lhs.type_ {
@@ -34,7 +36,7 @@ fn defined_walk(
             } else {
                 panic!("Writing to memory, but the expression isn't a store");
             };
-            for evar in extract_expr(index, cur_addr, func_addr, tmp_db) {
+            for evar in extract_expr(index, cur_addr, func_addr, tmp_db, Globals::NONE) {
                 if let E::VP(v) = evar {
                     if v.derefs() == 1 {
                         out.push(v.base)
@@ -44,7 +46,7 @@ fn defined_walk(
         }
         bil::Type::Immediate(1) => (),
         bil::Type::Immediate(_) => {
-            if lhs.tmp || lhs.name == "RSP" {
+            if lhs.tmp || regs::is_stack_pointer(&lhs.name) {
                 return Vec::new();
             }
             if let Ok(reg) = Reg::from_str(lhs.name.as_str()) {
@@ -62,7 +64,7 @@ fn used_walk(
     func_addr: &Loc,
     tmp_db: &mut BTreeMap<Var, u64>,
 ) -> Vec<Var> {
-    use crate::constraints::generation::{extract_expr, E};
+    use crate::constraints::generation::{extract_expr, Globals, E};
     let mut out = Vec::new();
     //TODO dedup
     match lhs.type_ {
@@ -77,14 +79,14 @@ fn used_walk(
             } else {
                 panic!("Writing to memory, but the expression isn't a store");
             };
-            for evar in extract_expr(index, cur_addr, func_addr, tmp_db) {
+            for evar in extract_expr(index, cur_addr, func_addr, tmp_db, Globals::NONE) {
                 if let E::VP(v) = evar {
                     if !v.base.is_temp() && v.derefs() > 1 {
                         out.push(v.base)
                     }
                 }
             }
-            for evar in extract_expr(value, cur_addr, func_addr, tmp_db) {
+            for evar in extract_expr(value, cur_addr, func_addr, tmp_db, Globals::NONE) {
                 if let E::VP(v) = evar {
                     if !v.base.is_temp() && v.derefs() > 1 {
                         out.push(v.base)
@@ -94,10 +96,10 @@ fn used_walk(
         }
         bil::Type::Immediate(1) => (),
         bil::Type::Immediate(_) => {
-            if lhs.name == "RSP" {
+            if regs::is_stack_pointer(&lhs.name) {
                 return Vec::new();
             }
-            for evar in extract_expr(rhs, cur_addr, func_addr, tmp_db) {
+            for evar in extract_expr(rhs, cur_addr, func_addr, tmp_db, Globals::NONE) {
                 if let E::VP(v) = evar {
                     if !v.base.is_temp() && v.derefs() > 1 {
                         out.push(v.base)
@@ -136,37 +138,81 @@ pub fn promote_var(i: &LivePromoteVarIn) -> Vec<LivePromoteVarOut> {
     }]
 }
 
-pub fn call_defs(_i: &LiveCallDefsIn) -> Vec<LiveCallDefsOut> {
+pub fn call_defs(i: &LiveCallDefsIn) -> Vec<LiveCallDefsOut> {
     vec![LiveCallDefsOut {
-        vars: vec![Var::Register { register: Reg::RAX }],
+        vars: vec![Var::Register {
+            register: i.conv.ret,
+        }],
     }]
 }
 
-pub fn drop_stack(i: &LiveDropStackIn) -> Vec<LiveDropStackOut> {
-    if i.var.is_stack() {
+pub fn first_arg(i: &LiveFirstArgIn) -> Vec<LiveFirstArgOut> {
+    vec![LiveFirstArgOut {
+        var: Var::arg(0, i.base, i.conv),
+    }]
+}
+
+pub fn freed_args(i: &LiveFreedArgsIn) -> Vec<LiveFreedArgsOut> {
+    i.args
+        .iter()
+        .map(|n| LiveFreedArgsOut {
+            var: Var::arg(*n, i.base, i.conv),
+        })
+        .collect()
+}
+
+fn in_frame(var: &Var, func: &Loc) -> bool {
+    match *var {
+        Var::StackSlot { ref func_addr, .. } => func_addr == func,
+        _ => false,
+    }
+}
+
+pub fn drop_frame(i: &LiveDropFrameIn) -> Vec<LiveDropFrameOut> {
+    if in_frame(i.var, i.func) {
         Vec::new()
     } else {
-        vec![LiveDropStackOut {}]
+        vec![LiveDropFrameOut {}]
     }
 }
 
-pub fn drop_frame(i: &LiveDropFrameIn) -> Vec<LiveDropFrameOut> {
+pub fn drop_ret_frame(i: &LiveDropRetFrameIn) -> Vec<LiveDropRetFrameOut> {
+    if in_frame(i.var, i.func) {
+        Vec::new()
+    } else {
+        vec![LiveDropRetFrameOut {}]
+    }
+}
+
+pub fn caller_arg(i: &LiveCallerArgIn) -> Vec<LiveCallerArgOut> {
+    let ret_size = i.conv.word_size;
     match *i.var {
-        Var::StackSlot { ref func_addr, .. } => {
-            if func_addr == i.func {
-                Vec::new()
-            } else {
-                vec![LiveDropFrameOut {}]
-            }
-        }
-        _ => vec![LiveDropFrameOut {}],
+        Var::StackSlot {
+            ref func_addr,
+            offset,
+        } if func_addr == i.func && offset >= ret_size => vec![LiveCallerArgOut {
+            arg: Var::StackSlot {
+                func_addr: i.base.clone(),
+                offset: offset - ret_size,
+            },
+        }],
+        _ => Vec::new(),
     }
 }
 
-pub fn promote_reg(i: &LivePromoteRegIn) -> Vec<LivePromoteRegOut> {
-    vec![LivePromoteRegOut {
-        var: Var::Register { register: *i.reg },
-    }]
+pub fn stack_arg(i: &LiveStackArgIn) -> Vec<LiveStackArgOut> {
+    let ret_size = i.conv.word_size;
+    match *i.var {
+        Var::StackSlot {
+            ref func_addr,
+            offset,
+        } if func_addr == i.loc && offset >= ret_size + i.conv.shadow_space => {
+            vec![LiveStackArgOut {
+                offsets: vec![offset - ret_size],
+            }]
+        }
+        _ => Vec::new(),
+    }
 }
 
 pub fn entry_defined_promote(i: &LiveEntryDefinedPromoteIn) -> Vec<LiveEntryDefinedPromoteOut> {
@@ -233,7 +279,7 @@ fn build_struct(
 pub fn undef_live(i: &LiveUndefLiveIn) -> Vec<LiveUndefLiveOut> {
     let mut undefs = Vec::new();
     trace!("undef_live candidate: {}", i.loc);
-    let args = var_args();
+    let args = var_args(i.conv);
     for var in i.live {
         if !i.defined.contains(var) && args.contains(var) {
             undefs.push(var.clone());
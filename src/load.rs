@@ -1,12 +1,11 @@
 use crate::datalog::*;
 use crate::interned_string::InternedString;
-use bap::basic::{Bap, BasicDisasm, Image};
+use crate::regs;
+use bap::basic::{Arch, Bap, BasicDisasm, Image};
 use bap::high::bil::{Expression, Statement};
 use bap::high::bitvector::BitVector;
 use std::collections::BTreeSet;
 
-const STACK_MAX_DEPTH: usize = 1;
-
 #[derive(Debug, Eq, Clone, PartialEq, PartialOrd, Ord, Hash)]
 pub enum Stack {
     /// Stack tracking not in use
@@ -62,18 +61,20 @@ impl Loc {
     pub fn is_stacked(&self) -> bool {
         self.stack != Stack::NoStack
     }
-}
 
-macro_rules! vec_error {
-    ($e:expr) => {{
-        let name: ::bap::basic::Result<_> = $e;
-        match name {
-            Ok(i) => vec![i],
-            Err(_) => return Vec::new(),
+    /// Return sites on the call string of this location, innermost first
+    pub fn call_string(&self) -> Vec<&Loc> {
+        let mut out = Vec::new();
+        let mut stack = &self.stack;
+        while let Stack::Return(ref ret) = *stack {
+            out.push(&**ret);
+            stack = &ret.stack;
         }
-    }};
+        out
+    }
 }
 
+// Failures to load an image are reported by image_diagnostic
 macro_rules! get_image {
     ($bap:expr, $contents:expr) => {{
         match Image::from_data(&$bap, &$contents) {
@@ -110,42 +111,40 @@ pub fn dump_segments(i: &LoadDumpSegmentsIn) -> Vec<LoadDumpSegmentsOut> {
     })
 }
 
-pub fn dump_plt(i: &LoadDumpPltIn) -> Vec<LoadDumpPltOut> {
-    use mktemp::Temp;
-    use std::fs::File;
-    use std::io::Write;
-    use std::process::Command;
-    let elf_temp = Temp::new_file().unwrap();
-    let elf_path_buf = elf_temp.to_path_buf();
-    let elf_path = elf_path_buf.to_str().unwrap();
-    {
-        let mut elf_file = File::create(elf_path).unwrap();
-        elf_file.write_all(i.contents).unwrap();
-    }
-    let out: String = String::from_utf8(
-        Command::new("bash")
-            .arg("-c")
-            .arg(format!("objdump -d {} | grep plt\\>:", elf_path))
-            .output()
-            .expect("objdump grep pipeline failure")
-            .stdout,
-    )
-    .unwrap();
-    out.split('\n')
-        .filter(|x| *x != "")
-        .map(|line| {
-            let mut it = line.split(' ');
-            let addr64 = u64::from_str_radix(it.next().unwrap(), 16).unwrap();
-            let unparsed = it.next().unwrap_or_else(|| panic!("No name? {}", line));
-            let name = unparsed[1..].split('@').next().unwrap();
-            LoadDumpPltOut {
-                pad_name: name.to_string(),
-                pad_loc: Loc {
-                    file_name: InternedString::from_string(i.file_name),
-                    addr: addr64,
-                    stack: Stack::NoStack,
-                },
-            }
+// Names of the stubs or import address table slots calls to other objects go through, from a
+// single read of the file
+pub fn read_imports(i: &LoadReadImportsIn) -> Vec<LoadReadImportsOut> {
+    let pads = if crate::pe::is_pe(i.contents) {
+        crate::pe::import_slots(i.contents)
+            .map(|slots| {
+                slots
+                    .into_iter()
+                    .map(|slot| (slot.addr, slot.name))
+                    .collect()
+            })
+            .map_err(|e| format!("could not read import table: {}", e))
+    } else {
+        crate::elf::plt_pads(i.contents)
+            .map(|pads| pads.into_iter().map(|pad| (pad.addr, pad.name)).collect())
+            .map_err(|e| format!("could not read PLT: {}", e))
+    };
+    vec![LoadReadImportsOut { pads }]
+}
+
+// Failures are reported by import_diagnostic
+pub fn link_pads(i: &LoadLinkPadsIn) -> Vec<LoadLinkPadsOut> {
+    let pads = match *i.pads {
+        Ok(ref pads) => pads,
+        Err(_) => return Vec::new(),
+    };
+    pads.iter()
+        .map(|&(addr, ref name)| LoadLinkPadsOut {
+            pad_name: name.clone(),
+            pad_loc: Loc {
+                file_name: InternedString::from_string(i.file_name),
+                addr,
+                stack: Stack::NoStack,
+            },
         })
         .collect()
 }
@@ -173,25 +172,44 @@ pub fn dump_syms(i: &LoadDumpSymsIn) -> Vec<LoadDumpSymsOut> {
     })
 }
 
-pub fn lift(i: &LoadLiftIn) -> Vec<LoadLiftOut> {
-    use num_traits::cast::ToPrimitive;
+// Bytes starting at loc, if loc is an unstacked location inside the provided segment
+fn insn_bytes<'a>(
+    loc: &Loc,
+    file_name: &str,
+    seg_contents: &'a [u8],
+    start: u64,
+    end: u64,
+) -> Option<&'a [u8]> {
     // This is super inefficient if we load tons of files in
-    if i.loc.file_name != InternedString::from_string(i.file_name) {
-        return Vec::new();
+    if loc.file_name != InternedString::from_string(file_name) {
+        return None;
     }
-    let start = i.seg_start.to_u64().unwrap();
-    let addr = i.loc.addr.to_u64().unwrap();
-    let end = i.seg_end.to_u64().unwrap();
+    let addr = loc.addr;
     if (addr < start) || (addr > end) {
-        return vec![];
+        return None;
     }
-    if i.loc.is_stacked() {
-        return Vec::new();
+    if loc.is_stacked() {
+        return None;
     }
-    vec_error!(Bap::with(|bap| {
-        let bin: &[u8] = &i.seg_contents[((addr - start) as usize)..];
-        let disas = BasicDisasm::new(bap, *i.arch)?;
-        let code = disas.disasm(bin, addr)?;
+    Some(&seg_contents[((addr - start) as usize)..])
+}
+
+// Failures are reported by lift_diagnostic
+pub fn lift(i: &LoadLiftIn) -> Vec<LoadLiftOut> {
+    let bin = match insn_bytes(i.loc, i.file_name, i.seg_contents, *i.seg_start, *i.seg_end) {
+        Some(bin) => bin,
+        None => return Vec::new(),
+    };
+    let addr = i.loc.addr;
+    Bap::with(|bap| {
+        let disas = match BasicDisasm::new(bap, *i.arch) {
+            Ok(disas) => disas,
+            Err(_) => return Vec::new(),
+        };
+        let code = match disas.disasm(bin, addr) {
+            Ok(code) => code,
+            Err(_) => return Vec::new(),
+        };
         let len = code.len() as u64;
         let insn = code.insn();
         let sema = insn.semantics();
@@ -201,7 +219,7 @@ pub fn lift(i: &LoadLiftIn) -> Vec<LoadLiftOut> {
         let is_ret = insn.is_return();
         let fall = addr + len;
 
-        Ok(LoadLiftOut {
+        vec![LoadLiftOut {
             bil: stmts,
             disasm,
             fall: Loc {
@@ -211,8 +229,71 @@ pub fn lift(i: &LoadLiftIn) -> Vec<LoadLiftOut> {
             },
             call: is_call,
             ret: is_ret,
-        })
-    }))
+        }]
+    })
+}
+
+pub fn lift_diagnostic(i: &LoadLiftDiagnosticIn) -> Vec<LoadLiftDiagnosticOut> {
+    let bin = match insn_bytes(i.loc, i.file_name, i.seg_contents, *i.seg_start, *i.seg_end) {
+        Some(bin) => bin,
+        None => return Vec::new(),
+    };
+    let addr = i.loc.addr;
+    let decoded: ::bap::basic::Result<usize> = Bap::with(|bap| {
+        let disas = BasicDisasm::new(bap, *i.arch)?;
+        let code = disas.disasm(bin, addr)?;
+        Ok(code.len())
+    });
+    match decoded {
+        Ok(_) => Vec::new(),
+        Err(e) => vec![LoadLiftDiagnosticOut {
+            addr: Some(addr),
+            message: format!("could not lift instruction: {:?}", e),
+        }],
+    }
+}
+
+pub fn image_diagnostic(i: &LoadImageDiagnosticIn) -> Vec<LoadImageDiagnosticOut> {
+    match *i.result {
+        Err(ref message) => vec![LoadImageDiagnosticOut {
+            addr: None,
+            message: message.clone(),
+        }],
+        Ok(_) => Vec::new(),
+    }
+}
+
+pub fn arch_diagnostic(i: &LoadArchDiagnosticIn) -> Vec<LoadArchDiagnosticOut> {
+    match regs::CallConv::for_arch(i.arch, false) {
+        Some(_) => Vec::new(),
+        None => vec![LoadArchDiagnosticOut {
+            addr: None,
+            message: format!("unsupported architecture {}", i.arch),
+        }],
+    }
+}
+
+pub fn import_diagnostic(i: &LoadImportDiagnosticIn) -> Vec<LoadImportDiagnosticOut> {
+    match *i.pads {
+        Err(ref message) => vec![LoadImportDiagnosticOut {
+            addr: None,
+            message: message.clone(),
+        }],
+        Ok(_) => Vec::new(),
+    }
+}
+
+/// Context a call from src, returning to fall, enters its callee in, keeping at most depth return
+/// sites
+pub fn callee_stack(src: &Loc, fall: &Loc, depth: usize) -> Stack {
+    match src.stack {
+        Stack::NoStack => Stack::NoStack,
+        _ => {
+            let mut s = Stack::Return(Box::new(fall.clone())).deloop();
+            s.relimit(depth);
+            s
+        }
+    }
 }
 
 pub fn sema_succ(i: &LoadSemaSuccIn) -> Vec<LoadSemaSuccOut> {
@@ -220,17 +301,10 @@ pub fn sema_succ(i: &LoadSemaSuccIn) -> Vec<LoadSemaSuccOut> {
     if fall {
         targets.push(i.fall.addr);
     }
-    let stack = match &i.src.stack {
-        &Stack::NoStack => Stack::NoStack,
-        s => {
-            if i.is_call {
-                let mut s = Stack::Return(Box::new(i.fall.clone())).deloop();
-                s.relimit(STACK_MAX_DEPTH);
-                s
-            } else {
-                s.clone()
-            }
-        }
+    let stack = if i.is_call {
+        callee_stack(i.src, i.fall, *i.depth)
+    } else {
+        i.src.stack.clone()
     };
     targets
         .into_iter()
@@ -295,30 +369,93 @@ fn is_const(e: &Expression) -> bool {
     }
 }
 
+/// Target expressions of the non-constant jumps in an instruction's semantics
+pub fn computed_jumps(bil: &[Statement]) -> Vec<&Expression> {
+    bil.iter()
+        .filter_map(|stmt| match *stmt {
+            Statement::Jump(ref e) if !is_const(e) => Some(e),
+            _ => None,
+        })
+        .collect()
+}
+
 pub fn is_computed_jump(i: &LoadIsComputedJumpIn) -> Vec<LoadIsComputedJumpOut> {
-    for stmt in i.bil.iter() {
-        match *stmt {
-            Statement::Jump(ref e) if !is_const(e) => return vec![LoadIsComputedJumpOut {}],
-            _ => (),
-        }
+    if computed_jumps(i.bil).is_empty() {
+        vec![]
+    } else {
+        vec![LoadIsComputedJumpOut {}]
     }
-    vec![]
 }
 
+// Address of the memory an indirect jump or call goes through, if it is constant, e.g. an import
+// address table slot
+pub fn indirect_slot(i: &LoadIndirectSlotIn) -> Vec<LoadIndirectSlotOut> {
+    use crate::fmt_str::const_collapse;
+    use num_traits::ToPrimitive;
+    computed_jumps(i.bil)
+        .into_iter()
+        .filter_map(|e| match *e {
+            Expression::Load { ref index, .. } => const_collapse(index, i.fallthrough.addr),
+            _ => None,
+        })
+        .filter_map(|addr| addr.to_u64())
+        .map(|addr| LoadIndirectSlotOut {
+            slot: Loc {
+                file_name: i.loc.file_name,
+                addr,
+                stack: if *i.is_call {
+                    callee_stack(i.loc, i.fallthrough, *i.depth)
+                } else {
+                    i.loc.stack.clone()
+                },
+            },
+        })
+        .collect()
+}
+
+// Failures are reported by image_diagnostic, from the same attempt
 pub fn get_arch(i: &LoadGetArchIn) -> Vec<LoadGetArchOut> {
     Bap::with(|bap| {
-        let image = get_image!(bap, i.contents);
-        vec![LoadGetArchOut {
-            arch: image.arch().unwrap(),
-        }]
+        let arch = match Image::from_data(&bap, &i.contents) {
+            Ok(image) => image
+                .arch()
+                .map_err(|e| format!("could not determine architecture: {:?}", e)),
+            Err(e) => Err(format!("could not load image: {:?}", e)),
+        };
+        vec![LoadGetArchOut { arch }]
     })
 }
 
+pub fn arch_ok(i: &LoadArchOkIn) -> Vec<LoadArchOkOut> {
+    i.result
+        .iter()
+        .map(|&arch| LoadArchOkOut { arch })
+        .collect()
+}
+
+pub fn call_conv(i: &LoadCallConvIn) -> Vec<LoadCallConvOut> {
+    regs::CallConv::for_arch(i.arch, crate::pe::is_pe(i.contents))
+        .map(|conv| LoadCallConvOut { conv })
+        .into_iter()
+        .collect()
+}
+
+pub fn in_file(i: &LoadInFileIn) -> Vec<LoadInFileOut> {
+    if i.loc.file_name == InternedString::from_string(i.file_name) {
+        vec![LoadInFileOut {}]
+    } else {
+        Vec::new()
+    }
+}
+
 pub fn malloc_name(func_name: &str) -> bool {
     func_name.contains("malloc")
         || func_name.contains("calloc")
         || func_name == "_Znam"
         || func_name == "_Znwm"
+        || func_name == "HeapAlloc"
+        || func_name == "LocalAlloc"
+        || func_name == "GlobalAlloc"
 }
 
 pub fn is_malloc_name(i: &LoadIsMallocNameIn) -> Vec<LoadIsMallocNameOut> {
@@ -331,15 +468,33 @@ pub fn is_malloc_name(i: &LoadIsMallocNameIn) -> Vec<LoadIsMallocNameOut> {
 
 pub fn is_free_name(i: &LoadIsFreeNameIn) -> Vec<LoadIsFreeNameOut> {
     let s = i.func_name;
-    if (s == "free") || (s == "g_free") || (s == "_ZdaPv") || (s == "_ZdlPvm") {
+    if (s == "free")
+        || (s == "g_free")
+        || (s == "_ZdaPv")
+        || (s == "_ZdlPvm")
+        || (s == "LocalFree")
+        || (s == "GlobalFree")
+    {
         vec![LoadIsFreeNameOut { args: vec![0] }]
     } else if s == "qfree" {
         vec![LoadIsFreeNameOut { args: vec![1] }]
+    } else if s == "HeapFree" {
+        vec![LoadIsFreeNameOut { args: vec![2] }]
     } else {
         Vec::new()
     }
 }
 
+pub fn is_realloc_name(i: &LoadIsReallocNameIn) -> Vec<LoadIsReallocNameOut> {
+    match i.func_name.as_str() {
+        "realloc" | "reallocarray" | "reallocf" | "g_realloc" | "g_realloc_n" | "_recalloc"
+        | "LocalReAlloc" | "GlobalReAlloc" => {
+            vec![LoadIsReallocNameOut {}]
+        }
+        _ => Vec::new(),
+    }
+}
+
 pub fn is_returning_name(i: &LoadIsReturningNameIn) -> Vec<LoadIsReturningNameOut> {
     let s = i.func_name;
     if s == "abort" || s == "__stack_chk_fail" || s == "exit" {
@@ -349,6 +504,16 @@ pub fn is_returning_name(i: &LoadIsReturningNameIn) -> Vec<LoadIsReturningNameOu
     }
 }
 
+// Only unstacked locations are named, see report_unstacked_site
+pub fn sym_offset(i: &LoadSymOffsetIn) -> Vec<LoadSymOffsetOut> {
+    if i.loc.is_stacked() {
+        return Vec::new();
+    }
+    vec![LoadSymOffsetOut {
+        offset: i.loc.addr - i.base.addr,
+    }]
+}
+
 pub fn call_site_stack(i: &LoadCallSiteStackIn) -> Vec<LoadCallSiteStackOut> {
     let mut target_loc_adjusted = i.target_loc.clone();
     if !i.call_loc.is_stacked() {
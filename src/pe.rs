@@ -0,0 +1,347 @@
+//! pe contains a minimal reader for PE/COFF images, just sufficient to find the import address
+//! table slots and the functions the loader binds them to.
+//!
+//! Calls to imported functions go through their IAT slot, either directly (`call [slot]`) or via
+//! a thunk which jumps through it, so slots play the part PLT stubs do in ELF objects.
+use std::fmt;
+
+const PE32: u16 = 0x10b;
+const PE32_PLUS: u16 = 0x20b;
+
+const IMAGE_FILE_MACHINE_I386: u16 = 0x14c;
+const IMAGE_FILE_MACHINE_AMD64: u16 = 0x8664;
+
+// Index of the import table in the optional header's data directories
+const IMPORT_DIRECTORY: u64 = 1;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParseError {
+    /// The file does not start with a DOS header pointing to a PE signature
+    NotPe,
+    /// A header or table points outside the file
+    Truncated,
+    /// Optional header magic was neither PE32 nor PE32+
+    BadMagic(u16),
+    /// We don't know the calling convention for this machine
+    UnsupportedMachine(u16),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::NotPe => write!(f, "not a PE file"),
+            ParseError::Truncated => write!(f, "truncated or malformed PE file"),
+            ParseError::BadMagic(m) => write!(f, "unknown optional header magic {:#x}", m),
+            ParseError::UnsupportedMachine(m) => {
+                write!(f, "imports not supported for PE machine {:#x}", m)
+            }
+        }
+    }
+}
+
+type Result<T> = ::std::result::Result<T, ParseError>;
+
+/// An import address table slot, and the name of the function it is bound to
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ImportSlot {
+    pub name: String,
+    pub addr: u64,
+}
+
+struct Section {
+    virtual_addr: u64,
+    virtual_size: u64,
+    raw_offset: u64,
+    raw_size: u64,
+}
+
+struct Pe<'a> {
+    contents: &'a [u8],
+    is_64: bool,
+    image_base: u64,
+    import_rva: u64,
+    sections: Vec<Section>,
+}
+
+fn read_u16(b: &[u8], off: usize) -> Result<u16> {
+    let s = b.get(off..off + 2).ok_or(ParseError::Truncated)?;
+    Ok(u16::from(s[0]) | (u16::from(s[1]) << 8))
+}
+
+fn read_u32(b: &[u8], off: usize) -> Result<u32> {
+    let s = b.get(off..off + 4).ok_or(ParseError::Truncated)?;
+    let mut out = 0;
+    for (i, byte) in s.iter().enumerate() {
+        out |= u32::from(*byte) << (8 * i);
+    }
+    Ok(out)
+}
+
+fn read_u64(b: &[u8], off: usize) -> Result<u64> {
+    let lo = read_u32(b, off)?;
+    let hi = read_u32(b, off + 4)?;
+    Ok(u64::from(lo) | (u64::from(hi) << 32))
+}
+
+fn c_str(b: &[u8], off: usize) -> Result<String> {
+    let tail = b.get(off..).ok_or(ParseError::Truncated)?;
+    let len = tail.iter().position(|c| *c == 0).unwrap_or(tail.len());
+    Ok(String::from_utf8_lossy(&tail[..len]).into_owned())
+}
+
+/// Whether the contents look like a PE image, as opposed to e.g. an ELF object
+pub fn is_pe(contents: &[u8]) -> bool {
+    pe_header(contents).is_ok()
+}
+
+// Offset of the COFF header, just past the PE signature
+fn pe_header(contents: &[u8]) -> Result<usize> {
+    if !contents.starts_with(b"MZ") {
+        return Err(ParseError::NotPe);
+    }
+    let pe_off = read_u32(contents, 0x3C).map_err(|_| ParseError::NotPe)? as usize;
+    match contents.get(pe_off..pe_off + 4) {
+        Some(sig) if sig == b"PE\0\0" => Ok(pe_off + 4),
+        _ => Err(ParseError::NotPe),
+    }
+}
+
+impl<'a> Pe<'a> {
+    fn parse(contents: &'a [u8]) -> Result<Self> {
+        let coff = pe_header(contents)?;
+        match read_u16(contents, coff)? {
+            IMAGE_FILE_MACHINE_I386 | IMAGE_FILE_MACHINE_AMD64 => (),
+            m => return Err(ParseError::UnsupportedMachine(m)),
+        }
+        let num_sections = read_u16(contents, coff + 2)?;
+        let opt_size = read_u16(contents, coff + 16)?;
+        let opt = coff + 20;
+        let (is_64, image_base, dirs) = match read_u16(contents, opt)? {
+            PE32 => (false, u64::from(read_u32(contents, opt + 28)?), opt + 96),
+            PE32_PLUS => (true, read_u64(contents, opt + 24)?, opt + 112),
+            m => return Err(ParseError::BadMagic(m)),
+        };
+        let num_dirs = u64::from(read_u32(contents, dirs - 4)?);
+        let import_rva = if num_dirs > IMPORT_DIRECTORY {
+            u64::from(read_u32(contents, dirs + 8 * IMPORT_DIRECTORY as usize)?)
+        } else {
+            0
+        };
+
+        let mut sections = Vec::new();
+        let table = opt + opt_size as usize;
+        for i in 0..num_sections as usize {
+            let hdr = table + i * 40;
+            sections.push(Section {
+                virtual_size: u64::from(read_u32(contents, hdr + 8)?),
+                virtual_addr: u64::from(read_u32(contents, hdr + 12)?),
+                raw_size: u64::from(read_u32(contents, hdr + 16)?),
+                raw_offset: u64::from(read_u32(contents, hdr + 20)?),
+            });
+        }
+
+        Ok(Pe {
+            contents,
+            is_64,
+            image_base,
+            import_rva,
+            sections,
+        })
+    }
+
+    // File offset of the data at an RVA
+    fn offset(&self, rva: u64) -> Result<usize> {
+        self.sections
+            .iter()
+            .find(|s| {
+                rva >= s.virtual_addr && rva < s.virtual_addr + s.virtual_size.max(s.raw_size)
+            })
+            .and_then(|s| {
+                let off = rva - s.virtual_addr;
+                if off < s.raw_size {
+                    Some((s.raw_offset + off) as usize)
+                } else {
+                    None
+                }
+            })
+            .ok_or(ParseError::Truncated)
+    }
+
+    fn import_slots(&self) -> Result<Vec<ImportSlot>> {
+        let mut out = Vec::new();
+        if self.import_rva == 0 {
+            return Ok(out);
+        }
+        let entry_size = if self.is_64 { 8 } else { 4 };
+        let ordinal_flag = if self.is_64 { 1 << 63 } else { 1 << 31 };
+        let mut desc = self.offset(self.import_rva)?;
+        loop {
+            let lookup_rva = u64::from(read_u32(self.contents, desc)?);
+            let iat_rva = u64::from(read_u32(self.contents, desc + 16)?);
+            if iat_rva == 0 {
+                break;
+            }
+            // Bound images overwrite the IAT, so names come from the lookup table if there is one
+            let names_rva = if lookup_rva != 0 { lookup_rva } else { iat_rva };
+            let mut thunk = self.offset(names_rva)?;
+            for i in 0.. {
+                let entry = if self.is_64 {
+                    read_u64(self.contents, thunk)?
+                } else {
+                    u64::from(read_u32(self.contents, thunk)?)
+                };
+                if entry == 0 {
+                    break;
+                }
+                // Imports by ordinal have no name to match models against
+                if entry & ordinal_flag == 0 {
+                    // Skip the two byte hint preceding the name
+                    let name = c_str(self.contents, self.offset(entry & 0x7fff_ffff)? + 2)?;
+                    out.push(ImportSlot {
+                        name,
+                        addr: self.image_base + iat_rva + i * entry_size,
+                    });
+                }
+                thunk += entry_size as usize;
+            }
+            desc += 20;
+        }
+        Ok(out)
+    }
+}
+
+/// Finds all import address table slots in a PE image, along with the name of the function they
+/// are bound to.
+pub fn import_slots(contents: &[u8]) -> Result<Vec<ImportSlot>> {
+    Pe::parse(contents)?.import_slots()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IMAGE_BASE: u64 = 0x40_0000;
+    const SECTION_RVA: u64 = 0x1000;
+    const SECTION_OFFSET: usize = 0x200;
+
+    fn put(b: &mut Vec<u8>, off: usize, bytes: &[u8]) {
+        if b.len() < off + bytes.len() {
+            b.resize(off + bytes.len(), 0);
+        }
+        b[off..off + bytes.len()].copy_from_slice(bytes);
+    }
+
+    fn put_u16(b: &mut Vec<u8>, off: usize, v: u16) {
+        put(b, off, &v.to_le_bytes());
+    }
+
+    fn put_u32(b: &mut Vec<u8>, off: usize, v: u32) {
+        put(b, off, &v.to_le_bytes());
+    }
+
+    // File offset of data in the only section
+    fn at(rva: u64) -> usize {
+        SECTION_OFFSET + (rva - SECTION_RVA) as usize
+    }
+
+    // An image importing HeapAlloc by name, something by ordinal, then HeapFree by name, from a
+    // single DLL
+    fn image(is_64: bool) -> Vec<u8> {
+        let mut b = Vec::new();
+        put(&mut b, 0, b"MZ");
+        put_u32(&mut b, 0x3C, 0x40);
+        put(&mut b, 0x40, b"PE\0\0");
+        let coff = 0x44;
+        let machine = if is_64 {
+            IMAGE_FILE_MACHINE_AMD64
+        } else {
+            IMAGE_FILE_MACHINE_I386
+        };
+        put_u16(&mut b, coff, machine);
+        put_u16(&mut b, coff + 2, 1);
+        let opt = coff + 20;
+        let dirs = if is_64 {
+            put_u16(&mut b, opt, PE32_PLUS);
+            put(&mut b, opt + 24, &IMAGE_BASE.to_le_bytes());
+            opt + 112
+        } else {
+            put_u16(&mut b, opt, PE32);
+            put_u32(&mut b, opt + 28, IMAGE_BASE as u32);
+            opt + 96
+        };
+        let opt_size = dirs - opt + 16 * 8;
+        put_u16(&mut b, coff + 16, opt_size as u16);
+        put_u32(&mut b, dirs - 4, 16);
+        put_u32(&mut b, dirs + 8, SECTION_RVA as u32);
+
+        let section = opt + opt_size;
+        put(&mut b, section, b".idata\0\0");
+        put_u32(&mut b, section + 8, 0x100);
+        put_u32(&mut b, section + 12, SECTION_RVA as u32);
+        put_u32(&mut b, section + 16, 0x100);
+        put_u32(&mut b, section + 20, SECTION_OFFSET as u32);
+
+        let (lookup, iat, alloc_name, free_name): (u32, u32, u32, u32) =
+            (0x1040, 0x1060, 0x1080, 0x1090);
+        // One descriptor, followed by the null descriptor
+        put_u32(&mut b, at(SECTION_RVA), lookup);
+        put_u32(&mut b, at(SECTION_RVA) + 16, iat);
+        put_u32(&mut b, at(SECTION_RVA) + 20 + 16, 0);
+        let entry_size = if is_64 { 8 } else { 4 };
+        let ordinal: u64 = if is_64 { (1 << 63) | 5 } else { (1 << 31) | 5 };
+        for &table in &[lookup, iat] {
+            for (i, entry) in [u64::from(alloc_name), ordinal, u64::from(free_name), 0]
+                .iter()
+                .enumerate()
+            {
+                let off = at(u64::from(table)) + i * entry_size;
+                put(&mut b, off, &entry.to_le_bytes()[..entry_size]);
+            }
+        }
+        put(&mut b, at(u64::from(alloc_name)) + 2, b"HeapAlloc\0");
+        put(&mut b, at(u64::from(free_name)) + 2, b"HeapFree\0");
+        b.resize(SECTION_OFFSET + 0x100, 0);
+        b
+    }
+
+    fn slot(name: &str, addr: u64) -> ImportSlot {
+        ImportSlot {
+            name: name.to_string(),
+            addr,
+        }
+    }
+
+    #[test]
+    fn pe32_plus_imports() {
+        let iat = IMAGE_BASE + 0x1060;
+        assert_eq!(
+            import_slots(&image(true)),
+            Ok(vec![slot("HeapAlloc", iat), slot("HeapFree", iat + 16)])
+        );
+    }
+
+    #[test]
+    fn pe32_imports() {
+        let iat = IMAGE_BASE + 0x1060;
+        assert_eq!(
+            import_slots(&image(false)),
+            Ok(vec![slot("HeapAlloc", iat), slot("HeapFree", iat + 8)])
+        );
+    }
+
+    #[test]
+    fn truncated() {
+        let image = image(true);
+        assert!(is_pe(&image));
+        assert_eq!(
+            import_slots(&image[..SECTION_OFFSET + 0x10]),
+            Err(ParseError::Truncated)
+        );
+    }
+
+    #[test]
+    fn not_pe() {
+        assert!(!is_pe(b"\x7fELF"));
+        assert_eq!(import_slots(b"MZ"), Err(ParseError::NotPe));
+    }
+}
@@ -14,8 +14,8 @@ pub struct VarRef {
 }
 
 impl VarRef {
-    fn is_freed(&self) -> bool {
-        self.var.is_freed()
+    fn is_marker(&self) -> bool {
+        self.var.is_marker()
     }
 }
 
@@ -254,6 +254,9 @@ pub struct PointsTo {
     inner: BTreeMap<Var, FieldMap>,
     super_live: BTreeSet<Var>,
     frames: BTreeSet<Loc>,
+    // Frames of functions which called into the current frames, and are still live after they
+    // return
+    callers: BTreeSet<Loc>,
 }
 
 impl PointsTo {
@@ -280,13 +283,15 @@ impl PointsTo {
         }
     }
 
-    pub fn make_dup(&mut self, alloc_site: &Loc) {
+    pub fn make_dup(&mut self, alloc_site: &Loc, clone: &[Loc]) {
         let stale = Var::Alloc {
             site: alloc_site.clone(),
+            clone: clone.to_vec(),
             stale: true,
         };
         let fresh = Var::Alloc {
             site: alloc_site.clone(),
+            clone: clone.to_vec(),
             stale: false,
         };
         if self.super_live.contains(&fresh) {
@@ -321,13 +326,15 @@ impl PointsTo {
         }
     }
 
-    pub fn make_stale(&mut self, alloc_site: &Loc) {
+    pub fn make_stale(&mut self, alloc_site: &Loc, clone: &[Loc]) {
         let stale = Var::Alloc {
             site: alloc_site.clone(),
+            clone: clone.to_vec(),
             stale: true,
         };
         let fresh = Var::Alloc {
             site: alloc_site.clone(),
+            clone: clone.to_vec(),
             stale: false,
         };
         if self.super_live.remove(&fresh) {
@@ -372,6 +379,52 @@ impl PointsTo {
         }
     }
 
+    /// Renames the fresh heap object allocated at site from one clone to another, leaving stale
+    /// objects of the old clone alone.
+    pub fn rename_alloc(&mut self, site: &Loc, from: &[Loc], to: &[Loc]) {
+        let old = Var::Alloc {
+            site: site.clone(),
+            clone: from.to_vec(),
+            stale: false,
+        };
+        let new = Var::Alloc {
+            site: site.clone(),
+            clone: to.to_vec(),
+            stale: false,
+        };
+        if self.super_live.remove(&old) {
+            self.super_live.insert(new.clone());
+        }
+        if let Some(pt) = self.inner.remove(&old) {
+            self.inner.insert(new.clone(), pt);
+        }
+        for fm in self.inner.values_mut() {
+            let renamed: Vec<_> = fm
+                .unbounded
+                .iter()
+                .filter(|vr| vr.var == old)
+                .cloned()
+                .collect();
+            for vr in renamed {
+                fm.unbounded.remove(&vr);
+                fm.unbounded.insert(VarRef {
+                    var: new.clone(),
+                    offset: vr.offset,
+                });
+            }
+            for vs in fm.offsets.values_mut() {
+                let renamed: Vec<_> = vs.iter().filter(|vr| vr.var == old).cloned().collect();
+                for vr in renamed {
+                    vs.remove(&vr);
+                    vs.insert(VarRef {
+                        var: new.clone(),
+                        offset: vr.offset,
+                    });
+                }
+            }
+        }
+    }
+
     /// Gets the set of what a variable may point to, returning an empty set if unmapped, including
     /// potential free references
     // I want it to return the empty set when it finds no element, so it can't return a reference.
@@ -382,10 +435,11 @@ impl PointsTo {
         }
     }
 
-    /// Gets the set of what a variable may point to, not including any free references
+    /// Gets the set of what a variable may point to, not including any free or dead frame
+    /// references
     pub fn get(&self, v: &VarRef) -> VarSet {
         let mut out = VarSet::new();
-        out.extend(self.get_all(v).iter().filter(|x| !x.is_freed()).cloned());
+        out.extend(self.get_all(v).iter().filter(|x| !x.is_marker()).cloned());
         out
     }
 
@@ -408,6 +462,32 @@ impl PointsTo {
         }
         self.super_live.extend(other.super_live.iter().cloned());
         self.frames.extend(other.frames.iter().cloned());
+        self.callers.extend(other.callers.iter().cloned());
+    }
+
+    /// Removes the variables matching the predicate, as `remove_predicate` does, except for stack
+    /// slots still pointed to by a variable which survives. Like a freed heap object, those are
+    /// kept as pointees, and their contents replaced by a marker for the return at site which
+    /// popped their frame.
+    pub fn pop_frame<F: Fn(&Var) -> bool>(&mut self, f: F, site: &Loc) {
+        let escaped: BTreeSet<Var> = self
+            .inner
+            .iter()
+            .filter(|&(k, _)| !f(k))
+            .flat_map(|(_, fm)| fm.pt_to())
+            .filter(|v| v.is_stack() && f(v))
+            .collect();
+        self.remove_predicate(|v| f(v) && !escaped.contains(v));
+        let mut dead = VarSet::new();
+        dead.insert(VarRef {
+            var: Var::DeadFrame { site: site.clone() },
+            offset: Some(0),
+        });
+        for slot in escaped {
+            let mut fm = FieldMap::new();
+            fm.write(Some(0), dead.clone());
+            self.inner.insert(slot, fm);
+        }
     }
 
     /// Removes all references to a variable by predicate.
@@ -527,8 +607,59 @@ impl PointsTo {
         self.frames.clear()
     }
 
-    pub fn drop_stack(&mut self) {
-        self.gc(|v| !v.is_stack() && !v.is_dyn());
+    /// Records the current frames as callers, before entering a callee
+    pub fn push_callers(&mut self) {
+        let frames = self.frames.clone();
+        self.callers.extend(frames);
+    }
+    /// Forgets the caller being returned to, which is no longer below the current frames
+    pub fn pop_caller(&mut self, caller: &Loc) {
+        self.callers.remove(caller);
+    }
+    /// Whether a frame belongs to a function which called into the current frames
+    pub fn is_caller(&self, frame: &Loc) -> bool {
+        self.callers.contains(frame)
+    }
+
+    /// Copies the outgoing argument slots at the provided offsets from the stack pointer of the
+    /// current frames into the frame of a callee, as seen from its entry, where they sit above the
+    /// return address. Returns the callee's copies.
+    pub fn pass_stack_args(
+        &mut self,
+        callee: &Loc,
+        ret_size: usize,
+        offsets: &[usize],
+    ) -> Vec<Var> {
+        // A recursive call would overwrite the caller's slots with its own
+        if self.frames.contains(callee) {
+            return Vec::new();
+        }
+        let args: Vec<_> = self
+            .inner
+            .iter()
+            .filter_map(|(k, v)| match *k {
+                Var::StackSlot {
+                    ref func_addr,
+                    offset,
+                } if self.frames.contains(func_addr) && offsets.contains(&offset) => Some((
+                    Var::StackSlot {
+                        func_addr: callee.clone(),
+                        offset: offset + ret_size,
+                    },
+                    v.clone(),
+                )),
+                _ => None,
+            })
+            .collect();
+        let vars = args.iter().map(|(k, _)| k.clone()).collect();
+        self.inner.extend(args);
+        vars
+    }
+
+    /// Drops stack slots which are not reachable from registers, globals, or the provided
+    /// variables.
+    pub fn drop_stack(&mut self, keep: &[Var]) {
+        self.gc(|v| !v.is_stack() && !v.is_dyn() || keep.contains(v));
     }
 
     /// Performs a reachability test for dynamic variables and removes them if they are
@@ -541,8 +672,14 @@ impl PointsTo {
 
     pub fn purge_dead(&mut self, live: &[Var]) {
         let mut to_purge = Vec::new();
-        for key in self.inner.keys() {
-            if !live.contains(key) && !key.is_dyn() && !self.super_live.contains(key) {
+        for (key, fm) in &self.inner {
+            // Slots of popped frames are objects, like the heap, rather than variables
+            if !live.contains(key)
+                && !key.is_dyn()
+                && !key.is_global()
+                && !self.super_live.contains(key)
+                && !fm.pt_to().iter().any(|v| v.is_dead_frame())
+            {
                 to_purge.push(key.clone());
             }
         }
@@ -554,17 +691,105 @@ impl PointsTo {
 
     /// Finds all locations where v may have been freed.
     pub fn free_sites(&self, v: &Var) -> Vec<Loc> {
-        self.get(&VarRef {
-            var: v.clone(),
-            offset: Some(0),
-        })
-        .iter()
-        .flat_map(|d| self.get_var(&d.var).pt_to())
-        .filter_map(|pt| match pt {
-            Var::Freed { ref site } => Some(site.clone()),
-            _ => None,
+        self.freed_objects(v)
+            .into_iter()
+            .map(|(_, site)| site)
+            .collect()
+    }
+
+    /// Finds the objects v may point to which have been freed, paired with where they may have
+    /// been freed.
+    pub fn freed_objects(&self, v: &Var) -> Vec<(Var, Loc)> {
+        let mut out = Vec::new();
+        for d in self
+            .get(&VarRef {
+                var: v.clone(),
+                offset: Some(0),
+            })
+            .iter()
+        {
+            for pt in self.get_var(&d.var).pt_to() {
+                if let Var::Freed { site } = pt {
+                    out.push((d.var.clone(), site));
+                }
+            }
+        }
+        out
+    }
+
+    /// Finds the allocation sites of all heap objects still reachable in this state.
+    pub fn alloc_sites(&self) -> BTreeSet<Loc> {
+        self.inner
+            .keys()
+            .cloned()
+            .chain(self.pt_to())
+            .filter_map(|v| match v {
+                Var::Alloc { site, .. } => Some(site),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Finds the clones of heap objects allocated at site still reachable in this state.
+    pub fn alloc_clones(&self, site: &Loc) -> BTreeSet<Vec<Loc>> {
+        self.inner
+            .keys()
+            .cloned()
+            .chain(self.pt_to())
+            .filter_map(|v| match v {
+                Var::Alloc {
+                    site: ref v_site,
+                    ref clone,
+                    ..
+                } if v_site == site && !clone.is_empty() => Some(clone.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Whether an object allocated at the provided site may have been freed.
+    pub fn alloc_freed(&self, site: &Loc) -> bool {
+        self.inner.iter().any(|(k, fm)| match *k {
+            Var::Alloc {
+                site: ref k_site, ..
+            } if k_site == site => fm.pt_to().iter().any(|v| v.is_freed()),
+            _ => false,
         })
-        .collect()
+    }
+
+    /// Finds the returns which popped the frame a stack pointer held in v pointed into.
+    pub fn dead_frames(&self, v: &Var) -> Vec<Loc> {
+        let mut out = Vec::new();
+        for d in self
+            .get(&VarRef {
+                var: v.clone(),
+                offset: Some(0),
+            })
+            .iter()
+        {
+            for pt in self.get_var(&d.var).pt_to() {
+                if let Var::DeadFrame { site } = pt {
+                    out.push(site);
+                }
+            }
+        }
+        out
+    }
+
+    /// Finds all variables which point to an object freed at the provided site.
+    pub fn dangling(&self, free: &Loc) -> Vec<Var> {
+        let freed = Var::Freed { site: free.clone() };
+        self.inner
+            .iter()
+            .filter(|&(k, fm)| {
+                !k.is_temp()
+                    && fm
+                        .pt_to()
+                        .iter()
+                        .any(|d| !d.is_marker() && self.get_var(d).pt_to().contains(&freed))
+            })
+            .map(|(k, _)| k.clone())
+            .collect()
     }
 }
 
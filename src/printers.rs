@@ -5,6 +5,7 @@ use crate::load::Loc;
 use crate::points_to::VarRef;
 use crate::regs::Reg;
 use crate::var::Var;
+use crate::witness::{Edge, Step};
 use crate::{Config, LocType};
 use std::fmt::{Display, Formatter, Result};
 pub struct CB<'a, T: Display + 'a>(pub &'a Vec<T>);
@@ -30,12 +31,24 @@ impl Display for Config {
                 LocType::AddrAndStack => "addr+stack",
             }
         )?;
+        if self.uses_ctx() {
+            write!(f, "&k={}", self.stack_depth)?;
+        }
+        if self.heap_clone {
+            write!(f, "&heap_clone")?;
+        }
         if self.undef_hack {
             write!(f, "&undef_hack")?;
         }
         if self.load_only {
             write!(f, "&load_only")?;
         }
+        if self.leaks {
+            write!(f, "&leaks")?;
+        }
+        if self.alloc_spec.is_some() {
+            write!(f, "&alloc_spec")?;
+        }
         Ok(())
     }
 }
@@ -87,15 +100,22 @@ impl Display for Var {
             Var::Register { ref register, .. } => write!(f, "{}", register),
             Var::Alloc {
                 ref site,
+                ref clone,
                 ref stale,
             } => {
                 write!(f, "dyn@{}", site)?;
+                for ret in clone {
+                    write!(f, "+clone@{}", ret)?;
+                }
                 if *stale {
                     write!(f, "+stale")?;
                 }
                 Ok(())
             }
             Var::Freed { ref site } => write!(f, "freed@{}", site),
+            Var::DeadFrame { ref site } => write!(f, "dead@{}", site),
+            Var::Global { ref addr } => write!(f, "global@{}", addr),
+            Var::Code { ref addr } => write!(f, "code@{}", addr),
             Var::Constructed {
                 ref site,
                 ref serial,
@@ -139,6 +159,78 @@ impl Display for UafFlowResult {
     }
 }
 
+impl Display for UseAfterReturnResult {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "{}->{}", self.ret, self.use_)
+    }
+}
+
+impl Display for DoubleFreeResult {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "{}->{}", self.free, self.free2)
+    }
+}
+
+impl Display for InvalidFreeResult {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "{} frees {}", self.free, self.object)
+    }
+}
+
+impl Display for LeakResult {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "{} lost at {}", self.alloc, self.lost)
+    }
+}
+
+impl Display for LoadDiagnosticResult {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "{}", self.file_name)?;
+        if let Some(addr) = self.addr {
+            write!(f, "@0x{:x}", addr)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl Display for LocSymResult {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(
+            f,
+            "{}+0x{:x} ({})",
+            self.name, self.offset, self.loc.file_name
+        )
+    }
+}
+
+impl Display for UafWitnessResult {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(
+            f,
+            "{}->{}->{} ({})",
+            self.alloc, self.free, self.use_, self.var
+        )
+    }
+}
+
+impl Display for Edge {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match *self {
+            Edge::Start => write!(f, "free"),
+            Edge::Over => write!(f, "step"),
+            Edge::Call => write!(f, "call"),
+            Edge::Return => write!(f, "ret"),
+        }
+    }
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "{} {} ", self.edge, self.loc)?;
+        fmt_vec(f, &self.dangling)
+    }
+}
+
 impl Display for CallSiteResult {
     fn fmt(&self, f: &mut Formatter) -> Result {
         write!(f, "{}->{}", self.call_loc, self.target_loc)
@@ -1,5 +1,8 @@
+use bap::basic::Arch;
+
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash)]
 pub enum Reg {
+    // x86-64
     RAX,
     RBX,
     RCX,
@@ -8,6 +11,7 @@ pub enum Reg {
     RBP,
     RSI,
     RDI,
+    // R8-R12 are shared with 32-bit ARM, which uses the same names
     R8,
     R9,
     R10,
@@ -16,15 +20,165 @@ pub enum Reg {
     R13,
     R14,
     R15,
+    // AArch64
+    X0,
+    X1,
+    X2,
+    X3,
+    X4,
+    X5,
+    X6,
+    X7,
+    X8,
+    X9,
+    X10,
+    X11,
+    X12,
+    X13,
+    X14,
+    X15,
+    X16,
+    X17,
+    X18,
+    X19,
+    X20,
+    X21,
+    X22,
+    X23,
+    X24,
+    X25,
+    X26,
+    X27,
+    X28,
+    X29,
+    X30,
+    // 32-bit ARM
+    R0,
+    R1,
+    R2,
+    R3,
+    R4,
+    R5,
+    R6,
+    R7,
+    LR,
+    // i386
+    EAX,
+    EBX,
+    ECX,
+    EDX,
+    ESP,
+    EBP,
+    ESI,
+    EDI,
 }
 use self::Reg::*;
 
-pub const ARGS: &[Reg] = &[RDI, RSI, RDX, RCX, R8, R9];
-pub const CALLER_SAVED: &[Reg] = &[RAX, RCX, RDX, R8, R9, R10, R11];
-pub const RET_REG: Reg = RAX;
+// Which registers a platform passes arguments and returns results in, and which a call may
+// clobber. Arguments past the register arguments are passed on the stack.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash)]
+pub struct CallConv {
+    pub args: &'static [Reg],
+    pub caller_saved: &'static [Reg],
+    pub ret: Reg,
+    // Size of an argument slot on the stack, and of the return address pushed by a call
+    pub word_size: usize,
+    // Space the caller reserves below the stack arguments for the callee to spill registers to
+    pub shadow_space: usize,
+}
 
-pub fn caller_saved() -> Vec<Reg> {
-    CALLER_SAVED.to_vec()
+// Where a call passes one of its arguments
+#[derive(Debug, Eq, PartialEq)]
+pub enum ArgLoc {
+    Register(Reg),
+    // Offset from the stack pointer at the call instruction
+    Stack(usize),
+}
+
+pub const SYSV_X86_64: CallConv = CallConv {
+    args: &[RDI, RSI, RDX, RCX, R8, R9],
+    caller_saved: &[RAX, RCX, RDX, R8, R9, R10, R11],
+    ret: RAX,
+    word_size: 8,
+    shadow_space: 0,
+};
+
+pub const MS_X64: CallConv = CallConv {
+    args: &[RCX, RDX, R8, R9],
+    caller_saved: &[RAX, RCX, RDX, R8, R9, R10, R11],
+    ret: RAX,
+    word_size: 8,
+    shadow_space: 32,
+};
+
+pub const AAPCS64: CallConv = CallConv {
+    args: &[X0, X1, X2, X3, X4, X5, X6, X7],
+    caller_saved: &[
+        X0, X1, X2, X3, X4, X5, X6, X7, X8, X9, X10, X11, X12, X13, X14, X15, X16, X17, X18, X30,
+    ],
+    ret: X0,
+    word_size: 8,
+    shadow_space: 0,
+};
+
+pub const AAPCS32: CallConv = CallConv {
+    args: &[R0, R1, R2, R3],
+    caller_saved: &[R0, R1, R2, R3, R12, LR],
+    ret: R0,
+    word_size: 4,
+    shadow_space: 0,
+};
+
+pub const I386_CDECL: CallConv = CallConv {
+    args: &[],
+    caller_saved: &[EAX, ECX, EDX],
+    ret: EAX,
+    word_size: 4,
+    shadow_space: 0,
+};
+
+impl CallConv {
+    // BAP names architectures "x86_64", "x86", "aarch64", "armv7", "thumbv7", etc. Windows
+    // images use the Microsoft convention on x86-64, and cdecl like everyone else on i386.
+    // Other architectures have no supported convention.
+    pub fn for_arch(arch: &Arch, windows: bool) -> Option<CallConv> {
+        Self::for_arch_name(&arch.to_string(), windows)
+    }
+
+    fn for_arch_name(name: &str, windows: bool) -> Option<CallConv> {
+        let name = name.to_lowercase();
+        if name == "x86_64" {
+            if windows {
+                Some(MS_X64)
+            } else {
+                Some(SYSV_X86_64)
+            }
+        } else if name == "x86" || name.ends_with("86") {
+            Some(I386_CDECL)
+        } else if name.starts_with("aarch64") {
+            Some(AAPCS64)
+        } else if name.starts_with("arm") || name.starts_with("thumb") {
+            Some(AAPCS32)
+        } else {
+            None
+        }
+    }
+
+    pub fn arg(&self, n: usize) -> Option<Reg> {
+        self.args.get(n).cloned()
+    }
+
+    pub fn arg_loc(&self, n: usize) -> ArgLoc {
+        match self.arg(n) {
+            Some(reg) => ArgLoc::Register(reg),
+            None => ArgLoc::Stack(self.shadow_space + self.word_size * (n - self.args.len())),
+        }
+    }
+}
+
+// Stack pointers are modeled through stack slots rather than as registers
+pub fn is_stack_pointer(name: &str) -> bool {
+    name == "RSP" || name == "ESP" || name == "SP"
 }
 
 impl ::std::str::FromStr for Reg {
@@ -47,7 +201,100 @@ impl ::std::str::FromStr for Reg {
             "R13" => Ok(R13),
             "R14" => Ok(R14),
             "R15" => Ok(R15),
+            "X0" => Ok(X0),
+            "X1" => Ok(X1),
+            "X2" => Ok(X2),
+            "X3" => Ok(X3),
+            "X4" => Ok(X4),
+            "X5" => Ok(X5),
+            "X6" => Ok(X6),
+            "X7" => Ok(X7),
+            "X8" => Ok(X8),
+            "X9" => Ok(X9),
+            "X10" => Ok(X10),
+            "X11" => Ok(X11),
+            "X12" => Ok(X12),
+            "X13" => Ok(X13),
+            "X14" => Ok(X14),
+            "X15" => Ok(X15),
+            "X16" => Ok(X16),
+            "X17" => Ok(X17),
+            "X18" => Ok(X18),
+            "X19" => Ok(X19),
+            "X20" => Ok(X20),
+            "X21" => Ok(X21),
+            "X22" => Ok(X22),
+            "X23" => Ok(X23),
+            "X24" => Ok(X24),
+            "X25" => Ok(X25),
+            "X26" => Ok(X26),
+            "X27" => Ok(X27),
+            "X28" => Ok(X28),
+            "X29" => Ok(X29),
+            "X30" => Ok(X30),
+            "R0" => Ok(R0),
+            "R1" => Ok(R1),
+            "R2" => Ok(R2),
+            "R3" => Ok(R3),
+            "R4" => Ok(R4),
+            "R5" => Ok(R5),
+            "R6" => Ok(R6),
+            "R7" => Ok(R7),
+            "LR" => Ok(LR),
+            "EAX" => Ok(EAX),
+            "EBX" => Ok(EBX),
+            "ECX" => Ok(ECX),
+            "EDX" => Ok(EDX),
+            "ESP" => Ok(ESP),
+            "EBP" => Ok(EBP),
+            "ESI" => Ok(ESI),
+            "EDI" => Ok(EDI),
             _ => Err(()),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn for_arch_name() {
+        assert_eq!(CallConv::for_arch_name("x86_64", false), Some(SYSV_X86_64));
+        assert_eq!(CallConv::for_arch_name("x86_64", true), Some(MS_X64));
+        assert_eq!(CallConv::for_arch_name("x86", false), Some(I386_CDECL));
+        assert_eq!(CallConv::for_arch_name("x86", true), Some(I386_CDECL));
+        assert_eq!(CallConv::for_arch_name("i686", false), Some(I386_CDECL));
+        assert_eq!(CallConv::for_arch_name("aarch64", false), Some(AAPCS64));
+        assert_eq!(CallConv::for_arch_name("AArch64", false), Some(AAPCS64));
+        assert_eq!(CallConv::for_arch_name("armv7", false), Some(AAPCS32));
+        assert_eq!(CallConv::for_arch_name("thumbv7", false), Some(AAPCS32));
+        assert_eq!(CallConv::for_arch_name("mips", false), None);
+    }
+
+    #[test]
+    fn arg_loc() {
+        assert_eq!(SYSV_X86_64.arg_loc(1), ArgLoc::Register(RSI));
+        assert_eq!(SYSV_X86_64.arg_loc(7), ArgLoc::Stack(8));
+        assert_eq!(MS_X64.arg_loc(4), ArgLoc::Stack(32));
+        assert_eq!(I386_CDECL.arg_loc(0), ArgLoc::Stack(0));
+        assert_eq!(I386_CDECL.arg_loc(2), ArgLoc::Stack(8));
+    }
+
+    #[test]
+    fn from_str() {
+        assert_eq!(Reg::from_str("RDI"), Ok(RDI));
+        assert_eq!(Reg::from_str("R8"), Ok(R8));
+        assert_eq!(Reg::from_str("X0"), Ok(X0));
+        assert_eq!(Reg::from_str("X30"), Ok(X30));
+        assert_eq!(Reg::from_str("R0"), Ok(R0));
+        assert_eq!(Reg::from_str("LR"), Ok(LR));
+        assert_eq!(Reg::from_str("EAX"), Ok(EAX));
+        assert_eq!(Reg::from_str("rdi"), Err(()));
+        // Stack pointers are modeled as stack slots, not registers
+        assert!(is_stack_pointer("SP"));
+        assert!(is_stack_pointer("ESP"));
+        assert!(!is_stack_pointer("RBP"));
+    }
+}
@@ -0,0 +1,111 @@
+//! summaries models library functions we only see through the PLT, using the table in
+//! mycroft/summaries.json.
+//!
+//! Each summary lists which arguments the function dereferences, whether its return value aliases
+//! one of its arguments, and whether it returns a fresh allocation. Functions like `getline`,
+//! which may free the buffer passed to them by reference and store a new one in its place, are
+//! described by the argument holding that reference.
+use crate::alloc_spec::AllocResult;
+use crate::constraints::{Constraint, VarPath};
+use crate::datalog::*;
+use crate::var::Var;
+use std::collections::BTreeMap;
+
+/// What the return value of a summarized function points to
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ReturnAlias {
+    /// The same address as was passed in this argument
+    Arg(usize),
+    /// Somewhere inside the object this argument points to
+    Interior(usize),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Summary {
+    name: String,
+    #[serde(default)]
+    derefs: Vec<usize>,
+    #[serde(default)]
+    returns: Option<ReturnAlias>,
+    #[serde(default)]
+    allocates: Option<AllocResult>,
+    #[serde(default)]
+    reallocates: Option<usize>,
+}
+
+lazy_static! {
+    static ref SUMMARIES: BTreeMap<String, Summary> = {
+        let summaries: Vec<Summary> =
+            ::serde_json::from_str(include_str!("../mycroft/summaries.json"))
+                .expect("malformed mycroft/summaries.json");
+        summaries.into_iter().map(|s| (s.name.clone(), s)).collect()
+    };
+}
+
+// Datalog functions
+
+pub fn derefs(i: &SummariesDerefsIn) -> Vec<SummariesDerefsOut> {
+    SUMMARIES
+        .get(i.func_name)
+        .into_iter()
+        .flat_map(|s| s.derefs.iter())
+        .map(|n| SummariesDerefsOut {
+            arg: Var::arg(*n, i.base, i.conv),
+        })
+        .collect()
+}
+
+pub fn allocates(i: &SummariesAllocatesIn) -> Vec<SummariesAllocatesOut> {
+    SUMMARIES
+        .get(i.func_name)
+        .and_then(|s| s.allocates)
+        .map(|result| SummariesAllocatesOut { result })
+        .into_iter()
+        .collect()
+}
+
+pub fn constraints(i: &SummariesConstraintsIn) -> Vec<SummariesConstraintsOut> {
+    let summary = match SUMMARIES.get(i.func_name) {
+        Some(summary) => summary,
+        None => return Vec::new(),
+    };
+    let arg = |n| VarPath::var(Var::arg(n, i.base, i.conv));
+    let mut c = Vec::new();
+    if let Some(returns) = summary.returns {
+        let rhs = match returns {
+            ReturnAlias::Arg(n) => arg(n),
+            ReturnAlias::Interior(n) => arg(n).unknown(),
+        };
+        c.push(Constraint {
+            lhs: VarPath::reg(i.conv.ret),
+            rhss: vec![rhs],
+        });
+    }
+    // The free of the old buffer has to happen before the new one is stored, so both are emitted
+    // here in order rather than through free_call and malloc_call.
+    if let Some(n) = summary.reallocates {
+        c.push(Constraint {
+            lhs: arg(n).deref().deref(),
+            rhss: vec![VarPath {
+                base: Var::Freed {
+                    site: i.loc.clone(),
+                },
+                offsets: vec![Some(0)],
+            }],
+        });
+        c.push(Constraint {
+            lhs: arg(n).deref(),
+            rhss: vec![VarPath::addr(Var::Alloc {
+                site: i.loc.clone(),
+                clone: Vec::new(),
+                stale: false,
+            })],
+        });
+    }
+    if c.is_empty() {
+        Vec::new()
+    } else {
+        vec![SummariesConstraintsOut { c }]
+    }
+}
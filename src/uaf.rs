@@ -1,5 +1,4 @@
 use crate::datalog::*;
-use crate::var::Var;
 
 pub fn reads_vars(i: &UafReadsVarsIn) -> Vec<UafReadsVarsOut> {
     //TODO this is not well modularized
@@ -8,9 +7,3 @@ pub fn reads_vars(i: &UafReadsVarsIn) -> Vec<UafReadsVarsOut> {
         .map(|v| UafReadsVarsOut { v })
         .collect()
 }
-
-pub fn use_vars(i: &UafUseVarsIn) -> Vec<UafUseVarsOut> {
-    vec![UafUseVarsOut {
-        v: Var::Register { register: *i.r },
-    }]
-}
@@ -1,7 +1,7 @@
 use crate::datalog::*;
 use crate::load::Loc;
 use crate::points_to::PointsTo;
-use crate::regs::{Reg, ARGS, CALLER_SAVED, RET_REG};
+use crate::regs::Reg;
 use crate::var::Var;
 use bap::high::bil::{Statement, Type, Variable};
 use std::collections::BTreeSet;
@@ -10,7 +10,13 @@ use std::str::FromStr;
 #[derive(Debug, Clone, Hash, Ord, PartialOrd, Eq, PartialEq)]
 pub enum KillSpec {
     Registers(Vec<Reg>),
-    StackFrame(Loc),
+    /// Frame of the function based at `base` is popped by the return at `ret`, which passes back
+    /// only the `result` register
+    StackFrame {
+        base: Loc,
+        ret: Loc,
+        result: Reg,
+    },
 }
 
 impl KillSpec {
@@ -23,12 +29,25 @@ impl KillSpec {
         use crate::var::Var::*;
         match (self, v) {
             (&Registers(ref rs), &Register { ref register, .. }) => rs.contains(register),
-            (&StackFrame(ref l), &StackSlot { ref func_addr, .. }) => func_addr == l,
-            (&StackFrame(_), &Register { ref register, .. }) => register != &RET_REG,
+            (&StackFrame { ref base, .. }, &StackSlot { ref func_addr, .. }) => func_addr == base,
+            (&StackFrame { ref result, .. }, &Register { ref register, .. }) => register != result,
             _ => false,
         }
     }
     pub fn purge_pts(&self, pts: &mut PointsTo) {
+        if let KillSpec::StackFrame { ref base, .. } = *self {
+            // A recursive activation shares its slots with the caller's frame, which stays live
+            if pts.is_caller(base) {
+                pts.remove_predicate(|v| !v.is_stack() && self.kill(v));
+                return;
+            }
+        }
+        // Slots of a popped frame which are pointed to from outside it are marked rather than
+        // dropped, so later dereferences can be reported
+        if let KillSpec::StackFrame { ref ret, .. } = *self {
+            pts.pop_frame(|v| self.kill(v), ret);
+            return;
+        }
         pts.remove_predicate(|v| self.kill(v));
     }
 }
@@ -43,7 +62,11 @@ pub fn killspec_regs(_i: &UseDefKillspecRegsIn) -> Vec<UseDefKillspecRegsOut> {
 
 pub fn stack_wipe(i: &UseDefStackWipeIn) -> Vec<UseDefStackWipeOut> {
     vec![UseDefStackWipeOut {
-        ks: KillSpec::StackFrame(i.base.clone()),
+        ks: KillSpec::StackFrame {
+            base: i.base.clone(),
+            ret: i.loc.clone(),
+            result: i.conv.ret,
+        },
     }]
 }
 
@@ -94,6 +117,12 @@ pub fn defines(i: &UseDefDefinesIn) -> Vec<UseDefDefinesOut> {
     }]
 }
 
+pub fn call_defines(i: &UseDefCallDefinesIn) -> Vec<UseDefCallDefinesOut> {
+    vec![UseDefCallDefinesOut {
+        registers: i.conv.caller_saved.to_vec(),
+    }]
+}
+
 pub fn expand_registers(i: &UseDefExpandRegistersIn) -> Vec<UseDefExpandRegistersOut> {
     i.registers
         .iter()
@@ -111,18 +140,8 @@ pub fn exclude_registers(i: &UseDefExcludeRegistersIn) -> Vec<UseDefExcludeRegis
     }
 }
 
-pub fn exclude_call_registers(
-    i: &UseDefExcludeCallRegistersIn,
-) -> Vec<UseDefExcludeCallRegistersOut> {
-    if CALLER_SAVED.contains(i.register) {
-        Vec::new()
-    } else {
-        vec![UseDefExcludeCallRegistersOut {}]
-    }
-}
-
 pub fn only_args(i: &UseDefOnlyArgsIn) -> Vec<UseDefOnlyArgsOut> {
-    if !ARGS.contains(&i.register) {
+    if !i.conv.args.contains(i.register) {
         Vec::new()
     } else {
         vec![UseDefOnlyArgsOut {}]
@@ -130,7 +149,7 @@ pub fn only_args(i: &UseDefOnlyArgsIn) -> Vec<UseDefOnlyArgsOut> {
 }
 
 pub fn only_ret(i: &UseDefOnlyRetIn) -> Vec<UseDefOnlyRetOut> {
-    if i.register == &RET_REG {
+    if *i.register == i.conv.ret {
         vec![UseDefOnlyRetOut {}]
     } else {
         Vec::new()
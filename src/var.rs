@@ -1,23 +1,65 @@
 use crate::load::Loc;
-use crate::regs::{Reg, ARGS};
+use crate::regs::{ArgLoc, CallConv, Reg};
 
 #[derive(Clone, Eq, Ord, Hash, PartialOrd, PartialEq, Debug)]
 pub enum Var {
-    StackSlot { func_addr: Loc, offset: usize },
-    Register { register: Reg },
-    Temp { serial: u32 },
-    Alloc { site: Loc, stale: bool },
-    Freed { site: Loc },
-    Constructed { site: Loc, serial: usize },
+    StackSlot {
+        func_addr: Loc,
+        offset: usize,
+    },
+    Register {
+        register: Reg,
+    },
+    Temp {
+        serial: u32,
+    },
+    // clone holds the return sites heap cloning renamed the object after, innermost first
+    Alloc {
+        site: Loc,
+        clone: Vec<Loc>,
+        stale: bool,
+    },
+    Freed {
+        site: Loc,
+    },
+    Constructed {
+        site: Loc,
+        serial: usize,
+    },
+    // Stands in for a stack slot in a frame which was popped by the return at site
+    DeadFrame {
+        site: Loc,
+    },
+    // Contents of the data segment starting at addr
+    Global {
+        addr: Loc,
+    },
+    // A function, as the target of a function pointer
+    Code {
+        addr: Loc,
+    },
 }
 
-pub fn var_args() -> Vec<Var> {
-    ARGS.iter()
+pub fn var_args(conv: &CallConv) -> Vec<Var> {
+    conv.args
+        .iter()
         .map(|reg| Var::Register { register: *reg })
         .collect()
 }
 
 impl Var {
+    // Variable holding the nth (0-indexed) general purpose argument at a call site in the function
+    // based at func_addr. Stack arguments are at the bottom of the caller's frame.
+    pub fn arg(n: usize, func_addr: &Loc, conv: &CallConv) -> Var {
+        match conv.arg_loc(n) {
+            ArgLoc::Register(register) => Var::Register { register },
+            ArgLoc::Stack(offset) => Var::StackSlot {
+                func_addr: func_addr.clone(),
+                offset,
+            },
+        }
+    }
+
     // Creates a temporary variable by doing string munging to extract the bap unique number from
     // the variable name.
     pub fn temp(name: &str) -> Var {
@@ -36,7 +78,8 @@ impl Var {
                 ..
             }
             | Var::Alloc { ref site, .. }
-            | Var::Freed { ref site, .. } => site.is_stacked(),
+            | Var::Freed { ref site, .. }
+            | Var::DeadFrame { ref site } => site.is_stacked(),
             _ => false,
         }
     }
@@ -62,6 +105,25 @@ impl Var {
         }
     }
 
+    pub fn is_dead_frame(&self) -> bool {
+        match *self {
+            Var::DeadFrame { .. } => true,
+            _ => false,
+        }
+    }
+
+    // Markers record how a pointer went bad, and never point anywhere themselves
+    pub fn is_marker(&self) -> bool {
+        self.is_freed() || self.is_dead_frame()
+    }
+
+    pub fn is_global(&self) -> bool {
+        match *self {
+            Var::Global { .. } => true,
+            _ => false,
+        }
+    }
+
     pub fn is_stack(&self) -> bool {
         match *self {
             Var::StackSlot { .. } => true,
@@ -0,0 +1,126 @@
+//! witness reconstructs a concrete instruction path from a free to a use for a reported
+//! use-after-free, annotating each step with the variables which point to the freed object.
+//!
+//! Paths are searched for breadth first over the same edges flow information propagates along,
+//! restricted to locations where the object freed at the free site is still considered freed, so
+//! every prefix of the path is one the analysis considered feasible.
+use crate::datalog::Database;
+use crate::load::Loc;
+use crate::points_to::PointsTo;
+use crate::var::Var;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// How control reached a step of a witness path from the previous step
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Edge {
+    /// First step in the path, the free call
+    Start,
+    /// Intraprocedural successor, or stepping over a call whose effect is summarized
+    Over,
+    /// Entry into a called function
+    Call,
+    /// Return to a call site
+    Return,
+}
+
+/// One instruction on a witness path
+#[derive(Debug, Clone)]
+pub struct Step {
+    /// Location of the instruction
+    pub loc: Loc,
+    /// How this step was reached
+    pub edge: Edge,
+    /// Variables which point to the freed object on entry to this instruction
+    pub dangling: Vec<Var>,
+}
+
+/// Precomputed successor and points-to information needed to find witness paths.
+///
+/// Building this queries the whole flow relation, so build it once and reuse it across findings.
+pub struct Witnesses {
+    succs: BTreeMap<Loc, BTreeSet<(Loc, Edge)>>,
+    flow_in: BTreeMap<Loc, PointsTo>,
+}
+
+impl Witnesses {
+    /// Collects successor edges and flow information from a database whose rules have been run.
+    pub fn new(db: &mut Database) -> Self {
+        let mut succs = BTreeMap::new();
+        {
+            let mut add = |src: Loc, dst: Loc, edge: Edge| {
+                succs
+                    .entry(src)
+                    .or_insert_with(BTreeSet::new)
+                    .insert((dst, edge));
+            };
+            for x in db.query_succ_over() {
+                add(x.src, x.dst, Edge::Over);
+            }
+            // Calls with a fused effect are flowed over, even when the callee may not return
+            for x in db.query_call_site() {
+                add(x.call_loc, x.ret_loc, Edge::Over);
+            }
+            for x in db.query_succ_call() {
+                add(x.src, x.dst, Edge::Call);
+            }
+            for x in db.query_succ_ret() {
+                add(x.src, x.dst, Edge::Return);
+            }
+        }
+        let flow_in = db
+            .query_flow()
+            .into_iter()
+            .map(|x| (x.loc, x.pts))
+            .collect();
+        Witnesses { succs, flow_in }
+    }
+
+    /// Finds a shortest path from a free call to a use of the object it freed.
+    ///
+    /// Returns `None` if no path exists along which the object remains freed.
+    pub fn path(&self, free: &Loc, use_: &Loc) -> Option<Vec<Step>> {
+        let mut prev: BTreeMap<&Loc, (&Loc, Edge)> = BTreeMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(free);
+        while let Some(loc) = queue.pop_front() {
+            if loc == use_ {
+                break;
+            }
+            for &(ref dst, edge) in self.succs.get(loc).into_iter().flatten() {
+                if dst == free || prev.contains_key(dst) || self.dangling(free, dst).is_empty() {
+                    continue;
+                }
+                prev.insert(dst, (loc, edge));
+                queue.push_back(dst);
+            }
+        }
+
+        let mut out = Vec::new();
+        let mut loc = use_;
+        while let Some(&(src, edge)) = prev.get(loc) {
+            out.push(Step {
+                loc: loc.clone(),
+                edge,
+                dangling: self.dangling(free, loc),
+            });
+            loc = src;
+        }
+        if loc != free {
+            return None;
+        }
+        out.push(Step {
+            loc: free.clone(),
+            edge: Edge::Start,
+            dangling: Vec::new(),
+        });
+        out.reverse();
+        Some(out)
+    }
+
+    fn dangling(&self, free: &Loc, loc: &Loc) -> Vec<Var> {
+        self.flow_in
+            .get(loc)
+            .map(|pts| pts.dangling(free))
+            .unwrap_or_default()
+    }
+}
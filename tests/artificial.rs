@@ -1,5 +1,6 @@
 extern crate marduk;
-use marduk::{uaf, Config};
+use marduk::{uaf, AllocSpec, Config, Edge};
+use std::collections::BTreeSet;
 
 fn run_uaf(names: &[&'static str], expected_flow_bugs: usize, expected_ctx_bugs: usize) {
     let names: Vec<_> = names
@@ -9,7 +10,7 @@ fn run_uaf(names: &[&'static str], expected_flow_bugs: usize, expected_ctx_bugs:
     {
         let mut flow_mode = Config::CONTEXT_INSENSITIVE;
         flow_mode.undef_hack = true;
-        let mut db = uaf(&names, flow_mode);
+        let mut db = uaf(&names, flow_mode).unwrap();
         db.run_rules();
         let flow_bugs = db.query_uaf_flow();
         let found_flow_bugs = flow_bugs.len();
@@ -26,7 +27,7 @@ fn run_uaf(names: &[&'static str], expected_flow_bugs: usize, expected_ctx_bugs:
     {
         let mut ctx_mode = Config::CONTEXT_SENSITIVE;
         ctx_mode.undef_hack = true;
-        let mut db = uaf(&names, ctx_mode);
+        let mut db = uaf(&names, ctx_mode).unwrap();
         db.run_rules();
         let ctx_bugs = db.query_context_flow();
         let found_ctx_bugs = ctx_bugs.len();
@@ -47,6 +48,31 @@ fn func() {
     run_uaf(&["func"], 1, 1);
 }
 
+#[test]
+fn func_i386() {
+    run_uaf(&["func_i386"], 1, 1);
+}
+
+#[test]
+fn simple_i386() {
+    run_uaf(&["simple_i386"], 2, 2);
+}
+
+#[test]
+fn func_aarch64() {
+    run_uaf(&["func_aarch64"], 1, 1);
+}
+
+#[test]
+fn func_arm() {
+    run_uaf(&["func_arm"], 1, 1);
+}
+
+#[test]
+fn heap_free() {
+    run_uaf(&["heap_free.exe"], 1, 1);
+}
+
 #[test]
 fn link() {
     run_uaf(&["link", "external.so"], 2, 2);
@@ -112,3 +138,299 @@ fn undef_edge() {
 fn field_overwrite() {
     run_uaf(&["field_overwrite"], 1, 1);
 }
+
+#[test]
+fn realloc() {
+    run_uaf(&["realloc"], 1, 1);
+}
+
+#[test]
+fn summaries() {
+    run_uaf(&["summaries"], 2, 2);
+}
+
+#[test]
+fn strdup() {
+    run_uaf(&["strdup"], 2, 2);
+}
+
+#[test]
+fn fmt_str() {
+    run_uaf(&["fmt_str"], 2, 2);
+}
+
+#[test]
+fn printf_family() {
+    run_uaf(&["printf_family"], 2, 2);
+}
+
+#[test]
+fn printf_family_pie() {
+    run_uaf(&["printf_family_pie"], 2, 2);
+}
+
+#[test]
+fn global() {
+    run_uaf(&["global"], 1, 1);
+}
+
+#[test]
+fn indirect_call() {
+    run_uaf(&["indirect_call"], 2, 2);
+}
+
+#[test]
+fn switch() {
+    run_uaf(&["switch"], 1, 1);
+    run_uaf(&["switch_nopie"], 1, 1);
+}
+
+#[test]
+fn switch_pair() {
+    // Reading past the first table would jump into the second switch with the freed pointer
+    run_uaf(&["switch_pair"], 0, 0);
+    run_uaf(&["switch_pair_nopie"], 0, 0);
+}
+
+#[test]
+fn double_free() {
+    run_uaf(&["double_free"], 0, 0);
+    let mut db = uaf(
+        &["samples/artificial/double_free".to_string()],
+        Config::CONTEXT_INSENSITIVE,
+    )
+    .unwrap();
+    db.run_rules();
+    assert_eq!(db.query_double_free().len(), 1);
+}
+
+#[test]
+fn invalid_free() {
+    let mut db = uaf(
+        &["samples/artificial/invalid_free".to_string()],
+        Config::CONTEXT_INSENSITIVE,
+    )
+    .unwrap();
+    db.run_rules();
+    assert_eq!(db.query_invalid_free().len(), 2);
+}
+
+#[test]
+fn use_after_return() {
+    run_uaf(&["use_after_return"], 0, 0);
+    let mut db = uaf(
+        &["samples/artificial/use_after_return".to_string()],
+        Config::CONTEXT_INSENSITIVE,
+    )
+    .unwrap();
+    db.run_rules();
+    assert!(!db.query_use_after_return().is_empty());
+}
+
+#[test]
+fn recurse_stack() {
+    run_uaf(&["recurse_stack"], 0, 0);
+    let mut db = uaf(
+        &["samples/artificial/recurse_stack".to_string()],
+        Config::CONTEXT_INSENSITIVE,
+    )
+    .unwrap();
+    db.run_rules();
+    assert_eq!(db.query_use_after_return().len(), 0);
+}
+
+#[test]
+fn alloc_spec() {
+    let names = vec!["samples/artificial/link".to_string()];
+    let mut db = uaf(&names, Config::CONTEXT_INSENSITIVE).unwrap();
+    db.run_rules();
+    assert!(db.query_uaf_flow().is_empty());
+
+    let mut config = Config::CONTEXT_INSENSITIVE;
+    let spec = AllocSpec::from_file("samples/artificial/link_spec.json").unwrap();
+    config.alloc_spec = Some(Box::leak(Box::new(spec)));
+    let mut db = uaf(&names, config).unwrap();
+    db.run_rules();
+    assert_eq!(db.query_uaf_flow().len(), 1);
+}
+
+#[test]
+fn stack_depth() {
+    for depth in 2..4 {
+        let mut config = Config::CONTEXT_SENSITIVE;
+        config.stack_depth = depth;
+        let mut db = uaf(&["samples/artificial/func".to_string()], config).unwrap();
+        db.run_rules();
+        assert_eq!(db.query_context_flow().len(), 1);
+    }
+
+    // With no return sites kept, every context is the empty stack
+    let mut config = Config::CONTEXT_SENSITIVE;
+    config.stack_depth = 0;
+    let mut db = uaf(&["samples/artificial/func".to_string()], config).unwrap();
+    db.run_rules();
+    let flows = db.query_context_flow();
+    assert!(!flows.is_empty());
+    for flow in flows {
+        for loc in &[&flow.free, &flow.use_] {
+            assert!(loc.is_stacked());
+            assert!(loc.call_string().is_empty());
+        }
+    }
+
+    // Frees through two levels of wrapper are only told apart with two return sites
+    let names = vec!["samples/artificial/double_wrap".to_string()];
+    let mut config = Config::CONTEXT_SENSITIVE;
+    config.stack_depth = 1;
+    let mut db = uaf(&names, config).unwrap();
+    db.run_rules();
+    assert!(!db.query_context_flow().is_empty());
+    config.stack_depth = 2;
+    let mut db = uaf(&names, config).unwrap();
+    db.run_rules();
+    assert!(db.query_context_flow().is_empty());
+}
+
+#[test]
+fn heap_clone() {
+    let names = vec!["samples/artificial/xmalloc".to_string()];
+    let mut config = Config::CONTEXT_INSENSITIVE;
+    config.heap_clone = true;
+    let mut db = uaf(&names, config).unwrap();
+    db.run_rules();
+    assert_eq!(db.query_uaf_flow().len(), 0);
+    assert_eq!(db.query_double_free().len(), 0);
+
+    let mut config = Config::CONTEXT_SENSITIVE;
+    config.heap_clone = true;
+    let mut db = uaf(&names, config).unwrap();
+    db.run_rules();
+    assert_eq!(db.query_context_flow().len(), 0);
+    assert_eq!(db.query_double_free().len(), 0);
+}
+
+fn run_leak(name: &'static str) -> usize {
+    let mut config = Config::CONTEXT_INSENSITIVE;
+    config.leaks = true;
+    let mut db = uaf(&[format!("samples/artificial/{}", name)], config).unwrap();
+    db.run_rules();
+    db.query_leak().len()
+}
+
+#[test]
+fn leak() {
+    assert_eq!(run_leak("leak"), 1);
+}
+
+#[test]
+fn no_leak() {
+    assert_eq!(run_leak("safe"), 0);
+}
+
+#[test]
+fn plt_names() {
+    let mut db = uaf(
+        &["samples/artificial/simple".to_string()],
+        Config::LOAD_ONLY,
+    )
+    .unwrap();
+    db.run_rules();
+    let names: Vec<_> = db
+        .query_link_pad()
+        .into_iter()
+        .map(|pad| pad.pad_name)
+        .collect();
+    for expected in &["malloc", "free", "printf"] {
+        assert!(
+            names.iter().any(|name| name == expected),
+            "No PLT entry for {} in {:?}",
+            expected,
+            names
+        );
+    }
+}
+
+#[test]
+fn not_a_binary() {
+    let mut db = uaf(
+        &["samples/artificial/simple.c".to_string()],
+        Config::LOAD_ONLY,
+    )
+    .unwrap();
+    db.run_rules();
+    assert!(!db.query_load_diagnostic().is_empty());
+}
+
+#[test]
+fn missing_input() {
+    assert!(uaf(
+        &["samples/artificial/does_not_exist".to_string()],
+        Config::LOAD_ONLY
+    )
+    .is_err());
+}
+
+#[test]
+fn witness() {
+    let mut db = uaf(
+        &["samples/artificial/simple".to_string()],
+        Config::CONTEXT_INSENSITIVE,
+    )
+    .unwrap();
+    db.run_rules();
+    let mallocs = db.query_get_malloc_call();
+    assert_eq!(mallocs.len(), 1);
+    let flows = db.query_uaf_flow();
+    assert!(!flows.is_empty());
+    let witnesses = db.query_uaf_witness();
+    for w in &witnesses {
+        assert_eq!(w.alloc, mallocs[0].loc);
+    }
+    for flow in &flows {
+        assert!(
+            witnesses
+                .iter()
+                .any(|w| w.free == flow.free && w.use_ == flow.use_ && w.var == flow.var),
+            "No allocation site for {}",
+            flow
+        );
+    }
+}
+
+#[test]
+fn witness_path() {
+    let mut db = uaf(
+        &["samples/artificial/func".to_string()],
+        Config::CONTEXT_INSENSITIVE,
+    )
+    .unwrap();
+    db.run_rules();
+    let witnesses = marduk::Witnesses::new(&mut db);
+    let mut edges = BTreeSet::new();
+    for x in db.query_succ_over() {
+        edges.insert((x.src, x.dst, Edge::Over));
+    }
+    for x in db.query_call_site() {
+        edges.insert((x.call_loc, x.ret_loc, Edge::Over));
+    }
+    for x in db.query_succ_call() {
+        edges.insert((x.src, x.dst, Edge::Call));
+    }
+    for x in db.query_succ_ret() {
+        edges.insert((x.src, x.dst, Edge::Return));
+    }
+    let flows = db.query_uaf_flow();
+    assert!(!flows.is_empty());
+    for flow in flows {
+        let path = witnesses
+            .path(&flow.free, &flow.use_)
+            .unwrap_or_else(|| panic!("No witness path for {}", flow));
+        let first = path.first().unwrap();
+        assert_eq!((&first.loc, first.edge), (&flow.free, Edge::Start));
+        assert_eq!(path.last().unwrap().loc, flow.use_);
+        for pair in path.windows(2) {
+            let edge = (pair[0].loc.clone(), pair[1].loc.clone(), pair[1].edge);
+            assert!(edges.contains(&edge), "{:?} is not an edge", edge);
+        }
+    }
+}
@@ -15,7 +15,7 @@ fn run_uaf(
         .collect();
     let mut config = marduk::Config::CONTEXT_INSENSITIVE;
     config.undef_hack = undef_hack;
-    let mut db = uaf(&names, config);
+    let mut db = uaf(&names, config).unwrap();
     db.run_rules();
 
     let mut false_positives_found = 0;